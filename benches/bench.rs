@@ -8,7 +8,7 @@
 
 //! benchmarking for rlp
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use criterion::{criterion_group, criterion_main, Criterion};
 use ethnum::*;
 use fastrlp::*;
@@ -18,7 +18,7 @@ fn bench_encode(c: &mut Criterion) {
     c.bench_function("encode_u64", |b| {
         b.iter(|| {
             let mut out = BytesMut::new();
-            let _ = 0x1023_4567_89ab_cdefu64.encode(&mut out);
+            0x1023_4567_89ab_cdefu64.encode(&mut out);
         })
     });
     c.bench_function("encode_u256", |b| {
@@ -33,12 +33,89 @@ fn bench_encode(c: &mut Criterion) {
     c.bench_function("encode_1000_u64", |b| {
         b.iter(|| {
             let mut out = BytesMut::new();
-            fastrlp::encode_list(
-                (0..1000u64).into_iter().collect::<Vec<_>>().as_slice(),
-                &mut out,
-            );
+            fastrlp::encode_list((0..1000u64).collect::<Vec<_>>().as_slice(), &mut out);
         })
     });
+    c.bench_function("encode_iter_1000_u64", |b| {
+        b.iter(|| {
+            let mut out = BytesMut::new();
+            fastrlp::encode_iter(0..1000u64, &mut out);
+        })
+    });
+}
+
+#[derive(RlpEncodable)]
+struct Tx {
+    nonce: u64,
+    gas_price: u64,
+    gas_limit: u64,
+    to: [u8; 20],
+    value: U256,
+    data: Bytes,
+}
+
+/// `Encodable::encode` goes through `&mut dyn BufMut`, `encode_to` is
+/// monomorphized for the buffer type.
+fn bench_encode_dispatch(c: &mut Criterion) {
+    let numbers = (0..1000u64).collect::<Vec<_>>();
+    let txs = (0..100u64)
+        .map(|i| Tx {
+            nonce: i,
+            gas_price: 20_000_000_000,
+            gas_limit: 21_000,
+            to: [0xAB; 20],
+            value: U256::new(u128::from(i) << 64),
+            data: Bytes::from_static(&[0xCD; 4]),
+        })
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("encode_dispatch");
+    group.bench_function("dyn_1000_u64", |b| {
+        let mut out = BytesMut::with_capacity(numbers.length());
+        b.iter(|| {
+            out.clear();
+            numbers.encode(&mut out);
+        })
+    });
+    group.bench_function("static_1000_u64", |b| {
+        let mut out = BytesMut::with_capacity(numbers.length());
+        b.iter(|| {
+            out.clear();
+            numbers.encode_to(&mut out);
+        })
+    });
+    group.bench_function("dyn_100_tx", |b| {
+        let mut out = BytesMut::with_capacity(txs.length());
+        b.iter(|| {
+            out.clear();
+            txs.encode(&mut out);
+        })
+    });
+    group.bench_function("static_100_tx", |b| {
+        let mut out = BytesMut::with_capacity(txs.length());
+        b.iter(|| {
+            out.clear();
+            txs.encode_to(&mut out);
+        })
+    });
+    // References' `encode_to` goes through `encode`, as they may point to
+    // unsized values.
+    let tx_refs = txs.iter().collect::<Vec<_>>();
+    group.bench_function("dyn_100_tx_refs", |b| {
+        let mut out = BytesMut::with_capacity(tx_refs.length());
+        b.iter(|| {
+            out.clear();
+            tx_refs.encode(&mut out);
+        })
+    });
+    group.bench_function("static_100_tx_refs", |b| {
+        let mut out = BytesMut::with_capacity(tx_refs.length());
+        b.iter(|| {
+            out.clear();
+            tx_refs.encode_to(&mut out);
+        })
+    });
+    group.finish();
 }
 
 fn bench_decode(c: &mut Criterion) {
@@ -59,7 +136,7 @@ fn bench_decode(c: &mut Criterion) {
         })
     });
     c.bench_function("decode_1000_u64", |b| {
-        let input = (0..1000u64).into_iter().collect::<Vec<_>>();
+        let input = (0..1000u64).collect::<Vec<_>>();
         let mut data = BytesMut::new();
         fastrlp::encode_list(input.as_slice(), &mut data);
         b.iter(|| {
@@ -68,5 +145,5 @@ fn bench_decode(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_encode, bench_decode);
+criterion_group!(benches, bench_encode, bench_encode_dispatch, bench_decode);
 criterion_main!(benches);
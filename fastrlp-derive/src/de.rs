@@ -1,96 +1,602 @@
+use crate::utils::*;
 use proc_macro2::TokenStream;
 use quote::quote;
 
-pub fn impl_decodable(ast: &syn::DeriveInput) -> TokenStream {
-    let body = if let syn::Data::Struct(s) = &ast.data {
-        s
-    } else {
-        panic!("#[derive(RlpDecodable)] is only defined for structs.");
+pub fn impl_decodable(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let body = match &ast.data {
+        syn::Data::Struct(s) => s,
+        syn::Data::Enum(e) => return impl_decodable_enum(ast, e),
+        syn::Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &ast.ident,
+                "#[derive(RlpDecodable)] is not defined for unions",
+            ))
+        }
     };
 
-    let stmts: Vec<_> = body
-        .fields
+    let struct_attrs = parse_struct_attrs(&ast.attrs)?;
+    let fields = parse_fields(&body.fields)?;
+    check_default_fields(&fields, &struct_attrs)?;
+
+    // Structs with `#[rlp(borrow)]` fields borrow from the buffer, so they
+    // implement `BorrowDecodable` instead of `Decodable`.
+    let borrowed = fields.iter().any(|field| field.attrs.borrow);
+    if borrowed && ast.generics.lifetimes().next().is_none() {
+        return Err(syn::Error::new_spanned(
+            &ast.ident,
+            "structs with #[rlp(borrow)] fields must have a lifetime parameter",
+        ));
+    }
+    let stmts: Vec<_> = fields
         .iter()
-        .enumerate()
-        .map(|(i, field)| decodable_field(i, field))
+        .map(|field| decodable_field(field, &struct_attrs, &Mode::Plain))
         .collect();
     let name = &ast.ident;
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let generics = add_bounds(
+        &ast.generics,
+        &struct_attrs.bound,
+        fields.iter().filter(|field| !field.attrs.borrow),
+        quote! { fastrlp::Decodable },
+        quote! { for<'f> fastrlp::DecodableFields<'f> },
+    );
+    let generics = if borrowed && struct_attrs.bound.is_none() {
+        add_bounds(
+            &generics,
+            &None,
+            fields.iter().filter(|field| field.attrs.borrow),
+            quote! { fastrlp::BorrowDecodable<'de> },
+            quote! { fastrlp::DecodableFields<'de> },
+        )
+    } else {
+        generics
+    };
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let de_generics = add_de_lifetime(&generics, borrowed);
+    let (de_impl_generics, _, _) = de_generics.split_for_impl();
 
-    let impl_block = quote! {
-        impl #impl_generics fastrlp::Decodable for #name #ty_generics #where_clause {
-            fn decode(buf: &mut &[u8]) -> Result<Self, fastrlp::DecodeError> {
+    let check_consumed = check_payload_consumed(&struct_attrs, false);
+    let decode_list = quote! {
+        let rlp_head = fastrlp::Header::decode(buf)?;
+
+        if !rlp_head.list {
+            return Err(fastrlp::DecodeError::UnexpectedString);
+        }
+
+        let payload_length = rlp_head.payload_length;
+        let payload = &mut &buf[..payload_length];
+        let this = fastrlp::DecodableFields::decode_fields(payload)?;
+
+        #check_consumed
+        *buf = &buf[payload_length..];
+
+        Ok(this)
+    };
+    let impl_block = if borrowed {
+        quote! {
+            impl #de_impl_generics fastrlp::DecodableFields<'de> for #name #ty_generics #where_clause {
+                fn decode_fields(buf: &mut &'de [u8]) -> Result<Self, fastrlp::DecodeError> {
+                    Ok(Self {
+                        #(#stmts)*
+                    })
+                }
+            }
+
+            impl #de_impl_generics fastrlp::BorrowDecodable<'de> for #name #ty_generics #where_clause {
+                fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, fastrlp::DecodeError> {
+                    #decode_list
+                }
+            }
+        }
+    } else {
+        let (impl_generics, _, _) = generics.split_for_impl();
+        let type_name = name.to_string();
+        let context_stmts: Vec<_> = fields
+            .iter()
+            .map(|field| decodable_field(field, &struct_attrs, &Mode::Context(None)))
+            .collect();
+        let start = context_start(&fields);
+        let check_consumed = check_payload_consumed(&struct_attrs, true);
+        let bytes_stmts: Vec<_> = fields
+            .iter()
+            .map(|field| decodable_field(field, &struct_attrs, &Mode::Bytes))
+            .collect();
+        let check_bytes_consumed = check_payload_consumed(&struct_attrs, false);
+        let borrow_decodable = forward_borrow_decodable(name, &generics);
+
+        quote! {
+            impl #de_impl_generics fastrlp::DecodableFields<'de> for #name #ty_generics #where_clause {
+                fn decode_fields(buf: &mut &'de [u8]) -> Result<Self, fastrlp::DecodeError> {
+                    Ok(Self {
+                        #(#stmts)*
+                    })
+                }
+
+                fn decode_fields_with_context(
+                    buf: &mut &'de [u8],
+                ) -> Result<Self, fastrlp::DetailedDecodeError> {
+                    #start
+                    Ok(Self {
+                        #(#context_stmts)*
+                    })
+                }
+            }
+
+            impl #impl_generics fastrlp::Decodable for #name #ty_generics #where_clause {
+                fn decode(buf: &mut &[u8]) -> Result<Self, fastrlp::DecodeError> {
+                    #decode_list
+                }
+
+                fn decode_with_context(buf: &mut &[u8]) -> Result<Self, fastrlp::DetailedDecodeError> {
+                    let len = buf.len();
+                    let rlp_head = fastrlp::Header::decode(buf)?;
+
+                    if !rlp_head.list {
+                        return Err(fastrlp::DecodeError::UnexpectedString.into());
+                    }
+
+                    let header_length = len - buf.len();
+                    let payload_length = rlp_head.payload_length;
+                    let payload = &mut &buf[..payload_length];
+                    let this = fastrlp::DecodableFields::decode_fields_with_context(payload)
+                        .map_err(|e| e.nest(header_length, None).in_type(#type_name))?;
+
+                    #check_consumed
+                    *buf = &buf[payload_length..];
+
+                    Ok(this)
+                }
+
+                fn decode_bytes(buf: &mut fastrlp::Bytes) -> Result<Self, fastrlp::DecodeError> {
+                    let mut payload = fastrlp::split_list_payload(buf)?;
+                    let payload_length = payload.len();
+                    let this = {
+                        let buf = &mut payload;
+                        Self {
+                            #(#bytes_stmts)*
+                        }
+                    };
+
+                    let payload = &mut &payload[..];
+                    #check_bytes_consumed
+
+                    Ok(this)
+                }
+            }
+
+            #borrow_decodable
+        }
+    };
+
+    Ok(quote! {
+        const _: () = {
+            extern crate fastrlp;
+            #impl_block
+        };
+    })
+}
+
+fn impl_decodable_enum(ast: &syn::DeriveInput, body: &syn::DataEnum) -> syn::Result<TokenStream> {
+    let enum_attrs = parse_enum_attrs(&ast.attrs)?;
+    let tagging = enum_attrs.tagging;
+    let tag_consts = variant_tag_consts(body);
+    let variant_fields = body
+        .variants
+        .iter()
+        .map(|variant| parse_fields(&variant.fields))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    for (variant, fields) in body.variants.iter().zip(&variant_fields) {
+        check_variant_attrs(variant, fields)?;
+        check_no_flatten(fields)?;
+        check_no_borrow(fields)?;
+        if tagging == EnumTagging::Integer {
+            check_unit_variant(variant)?;
+        }
+    }
+
+    let name = &ast.ident;
+    let has_fields = variant_fields
+        .iter()
+        .flatten()
+        .any(|field| !field.attrs.skip);
+    let select_variant =
+        |context: bool| {
+            let into = if context {
+                quote! { .into() }
+            } else {
+                quote! {}
+            };
+            let arms = body.variants.iter().zip(&variant_fields).enumerate().map(
+                |(i, (variant, fields))| {
+                    let ident = &variant.ident;
+                    let tag = variant_tag_ident(i);
+                    let mode = if context {
+                        Mode::Context(Some(format!("{}::{}", name, ident)))
+                    } else {
+                        Mode::Plain
+                    };
+                    let fields = fields.iter().map(|field| {
+                        let member = &field.member;
+                        if field.attrs.skip {
+                            quote! { #member: Default::default(), }
+                        } else {
+                            let decode = decode_expr(field, &mode);
+                            quote! { #member: #decode?, }
+                        }
+                    });
+                    quote! {
+                        #tag => Self::#ident { #(#fields)* },
+                    }
+                },
+            );
+            quote! {
+                match tag {
+                    #(#arms)*
+                    _ => return Err(fastrlp::DecodeError::UnknownTag(tag) #into),
+                }
+            }
+        };
+    // Expression decoding the list of a variant's fields from `buf`, which
+    // starts `base` bytes into the value. The tag is the list's first
+    // element, or must already be bound to `tag`.
+    let decode_list = |context: bool, tag_in_list: bool, base: TokenStream| {
+        let into = if context {
+            quote! { .into() }
+        } else {
+            quote! {}
+        };
+        let (header_length, start) = if context {
+            (
+                quote! { let header_length = start_len - buf.len(); },
+                if has_fields {
+                    quote! { let len = #base + header_length + buf.len(); }
+                } else {
+                    quote! {}
+                },
+            )
+        } else {
+            (quote! {}, quote! {})
+        };
+        let read_tag = if tag_in_list {
+            quote! { let tag: u64 = fastrlp::Decodable::decode(buf)?; }
+        } else {
+            quote! {}
+        };
+        let select_variant = select_variant(context);
+        quote! {
+            {
+                let start_len = buf.len();
                 let rlp_head = fastrlp::Header::decode(buf)?;
 
                 if !rlp_head.list {
-                    return Err(fastrlp::DecodeError::UnexpectedString);
+                    return Err(fastrlp::DecodeError::UnexpectedString #into);
                 }
 
-                let started_len = buf.len();
-                let this = Self {
-                    #(#stmts)*
+                #header_length
+                let payload_length = rlp_head.payload_length;
+                let payload = &mut &buf[..payload_length];
+                let this = {
+                    let buf = &mut *payload;
+                    #start
+                    #read_tag
+                    #select_variant
                 };
 
-                let consumed = started_len - buf.len();
-                if consumed != rlp_head.payload_length {
+                if !payload.is_empty() {
                     return Err(fastrlp::DecodeError::ListLengthMismatch {
-                        expected: rlp_head.payload_length,
-                        got: consumed,
-                    });
+                        expected: payload_length,
+                        got: payload_length - payload.len(),
+                    } #into);
                 }
+                *buf = &buf[payload_length..];
 
-                Ok(this)
+                this
             }
         }
     };
+    let read_type_byte = quote! {
+        let tag = u64::from(*buf.first().ok_or(fastrlp::DecodeError::InputTooShort)?);
+        *buf = &buf[1..];
+    };
+    let decode_body = |context: bool| {
+        let into = if context {
+            quote! { .into() }
+        } else {
+            quote! {}
+        };
+        match tagging {
+            EnumTagging::List => {
+                let decode_list = decode_list(context, true, quote! { 0 });
+                quote! { Ok(#decode_list) }
+            }
+            // The EIP-2718 encoding, wrapped in a byte string.
+            EnumTagging::TypeByte => {
+                let decode_list =
+                    decode_list(context, false, quote! { envelope_header_length + 1 });
+                let envelope_header_length = if context {
+                    quote! { let envelope_header_length = start_len - buf.len(); }
+                } else {
+                    quote! {}
+                };
+                quote! {
+                    let start_len = buf.len();
+                    let rlp_head = fastrlp::Header::decode(buf)?;
 
-    quote! {
+                    if rlp_head.list {
+                        return Err(fastrlp::DecodeError::UnexpectedList #into);
+                    }
+
+                    #envelope_header_length
+                    let envelope_length = rlp_head.payload_length;
+                    let envelope = &mut &buf[..envelope_length];
+                    let this = {
+                        let buf = &mut *envelope;
+                        #read_type_byte
+                        #decode_list
+                    };
+
+                    if !envelope.is_empty() {
+                        return Err(fastrlp::DecodeError::ListLengthMismatch {
+                            expected: envelope_length,
+                            got: envelope_length - envelope.len(),
+                        } #into);
+                    }
+                    *buf = &buf[envelope_length..];
+
+                    Ok(this)
+                }
+            }
+            EnumTagging::Integer => {
+                let select_variant = select_variant(context);
+                quote! {
+                    let tag: u64 = fastrlp::Decodable::decode(buf)?;
+                    Ok(#select_variant)
+                }
+            }
+        }
+    };
+    let decode = decode_body(false);
+    let decode_with_context = decode_body(true);
+
+    let generics = add_bounds(
+        &ast.generics,
+        &enum_attrs.bound,
+        variant_fields.iter().flatten(),
+        quote! { fastrlp::Decodable },
+        quote! { for<'f> fastrlp::DecodableFields<'f> },
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let borrow_decodable = forward_borrow_decodable(name, &generics);
+    let decode_2718 = if tagging == EnumTagging::TypeByte {
+        let decode_list = decode_list(false, false, quote! {});
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Decodes the EIP-2718 encoding, the type byte followed by
+                /// the list of the variant's fields, without the byte string
+                /// of `Decodable::decode`.
+                pub fn decode_2718(buf: &mut &[u8]) -> Result<Self, fastrlp::DecodeError> {
+                    #read_type_byte
+                    Ok(#decode_list)
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let impl_block = quote! {
+        #tag_consts
+
+        impl #impl_generics fastrlp::Decodable for #name #ty_generics #where_clause {
+            fn decode(buf: &mut &[u8]) -> Result<Self, fastrlp::DecodeError> {
+                #decode
+            }
+
+            fn decode_with_context(buf: &mut &[u8]) -> Result<Self, fastrlp::DetailedDecodeError> {
+                #decode_with_context
+            }
+        }
+
+        #borrow_decodable
+        #decode_2718
+    };
+
+    Ok(quote! {
         const _: () = {
             extern crate fastrlp;
             #impl_block
         };
-    }
+    })
 }
 
-pub fn impl_decodable_wrapper(ast: &syn::DeriveInput) -> TokenStream {
-    let body = if let syn::Data::Struct(s) = &ast.data {
-        s
-    } else {
-        panic!("#[derive(RlpEncodableWrapper)] is only defined for structs.");
+pub fn impl_decodable_wrapper(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let body = expect_struct(ast, "RlpDecodableWrapper")?;
+    let bound = parse_wrapper_attrs(&ast.attrs)?;
+    let field = wrapped_field(body, "RlpDecodableWrapper")?;
+    let ident = &field.member;
+    let decode = decode_expr(&field, &Mode::Plain);
+    let decode_bytes = decode_expr(&field, &Mode::Bytes);
+    // The wrapper is transparent, so errors are not nested under the field.
+    let decode_with_context = match &field.attrs.with {
+        Some(path) => quote! { #path::decode(buf).map_err(fastrlp::DetailedDecodeError::from) },
+        None => quote! { fastrlp::Decodable::decode_with_context(buf) },
     };
 
-    assert_eq!(
-        body.fields.iter().count(),
-        1,
-        "#[derive(RlpEncodableWrapper)] is only defined for structs with one field."
-    );
-
     let name = &ast.ident;
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let generics = add_bounds(
+        &ast.generics,
+        &bound,
+        [&field],
+        quote! { fastrlp::Decodable },
+        quote! { for<'f> fastrlp::DecodableFields<'f> },
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let borrow_decodable = forward_borrow_decodable(name, &generics);
 
     let impl_block = quote! {
         impl #impl_generics fastrlp::Decodable for #name #ty_generics #where_clause {
             fn decode(buf: &mut &[u8]) -> Result<Self, fastrlp::DecodeError> {
-                Ok(Self(fastrlp::Decodable::decode(buf)?))
+                Ok(Self { #ident: #decode? })
+            }
+
+            fn decode_with_context(buf: &mut &[u8]) -> Result<Self, fastrlp::DetailedDecodeError> {
+                Ok(Self { #ident: #decode_with_context? })
+            }
+
+            fn decode_bytes(buf: &mut fastrlp::Bytes) -> Result<Self, fastrlp::DecodeError> {
+                Ok(Self { #ident: #decode_bytes? })
             }
         }
+
+        #borrow_decodable
     };
 
-    quote! {
+    Ok(quote! {
         const _: () = {
             extern crate fastrlp;
             #impl_block
         };
+    })
+}
+
+/// `BorrowDecodable` impl of a type implementing `Decodable`, so that it can
+/// be decoded in containers of borrowing types.
+fn forward_borrow_decodable(name: &syn::Ident, generics: &syn::Generics) -> TokenStream {
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let de_generics = add_de_lifetime(generics, false);
+    let (de_impl_generics, _, _) = de_generics.split_for_impl();
+    quote! {
+        impl #de_impl_generics fastrlp::BorrowDecodable<'de> for #name #ty_generics #where_clause {
+            fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, fastrlp::DecodeError> {
+                fastrlp::Decodable::decode(buf)
+            }
+        }
+    }
+}
+
+/// How the derived impl decodes its fields.
+enum Mode {
+    /// `Decodable::decode`, or `BorrowDecodable::borrow_decode` for
+    /// `#[rlp(borrow)]` fields.
+    Plain,
+    /// `Decodable::decode_bytes`, sharing the source of `Bytes` fields.
+    /// Flattened and `with` fields are decoded from a slice of it.
+    Bytes,
+    /// `Decodable::decode_with_context`, naming the field in errors. Enum
+    /// variants also name their type, as they have no `DecodableFields` impl.
+    Context(Option<String>),
+}
+
+/// Checks that the list payload was fully read, or skips the rest of it with
+/// `#[rlp(allow_trailing)]`.
+fn check_payload_consumed(struct_attrs: &StructAttrs, context: bool) -> TokenStream {
+    let into = if context {
+        quote! { .into() }
+    } else {
+        quote! {}
+    };
+    if struct_attrs.allow_trailing {
+        quote! {
+            while !payload.is_empty() {
+                let h = fastrlp::Header::decode(payload)?;
+                *payload = &payload[h.payload_length..];
+            }
+        }
+    } else {
+        quote! {
+            if !payload.is_empty() {
+                return Err(fastrlp::DecodeError::ListLengthMismatch {
+                    expected: payload_length,
+                    got: payload_length - payload.len(),
+                } #into);
+            }
+        }
     }
 }
 
-fn decodable_field(index: usize, field: &syn::Field) -> TokenStream {
-    let id = if let Some(ident) = &field.ident {
-        quote! { #ident }
+/// Records the start of the buffer, from which `Mode::Context` fields compute
+/// their offsets.
+fn context_start<'a>(fields: impl IntoIterator<Item = &'a RlpField<'a>>) -> TokenStream {
+    if fields.into_iter().any(|field| !field.attrs.skip) {
+        quote! { let len = buf.len(); }
     } else {
-        let index = syn::Index::from(index);
-        quote! { #index }
+        quote! {}
+    }
+}
+
+/// Expression decoding `field` from `buf`, of type `Result<_, E>` where `E` is
+/// the error type of `mode`.
+fn decode_expr(field: &RlpField, mode: &Mode) -> TokenStream {
+    let context = match mode {
+        Mode::Plain => {
+            let decode = if field.attrs.flatten {
+                quote! { fastrlp::DecodableFields::decode_fields }
+            } else {
+                decode_fn(&field.attrs)
+            };
+            return quote! { #decode(buf) };
+        }
+        Mode::Bytes => {
+            let decode = match &field.attrs.with {
+                _ if field.attrs.flatten => quote! { fastrlp::DecodableFields::decode_fields },
+                Some(path) => quote! { #path::decode },
+                None => return quote! { fastrlp::Decodable::decode_bytes(buf) },
+            };
+            return quote! { fastrlp::decode_bytes_with(buf, |buf| #decode(buf)) };
+        }
+        Mode::Context(type_name) => type_name,
+    };
+
+    let name = field.member.to_string();
+    let name = name.trim_start_matches("r#");
+    let (decode, segment) = if field.attrs.flatten {
+        (
+            quote! { fastrlp::DecodableFields::decode_fields_with_context(buf) },
+            quote! { None },
+        )
+    } else {
+        let decode = match &field.attrs.with {
+            Some(path) => quote! { #path::decode(buf).map_err(fastrlp::DetailedDecodeError::from) },
+            None => quote! { fastrlp::Decodable::decode_with_context(buf) },
+        };
+        (decode, quote! { Some(fastrlp::PathSegment::Field(#name)) })
     };
+    let in_type = context
+        .as_ref()
+        .map(|type_name| quote! { .in_type(#type_name) });
+    quote! {
+        {
+            let offset = len - buf.len();
+            #decode.map_err(|e| e.nest(offset, #segment) #in_type)
+        }
+    }
+}
+
+fn decodable_field(field: &RlpField, struct_attrs: &StructAttrs, mode: &Mode) -> TokenStream {
+    let id = &field.member;
+    let decode = decode_expr(field, mode);
 
-    quote! { #id: fastrlp::Decodable::decode(buf)?, }
+    if field.attrs.skip {
+        quote! { #id: Default::default(), }
+    } else if field.attrs.flatten {
+        quote! { #id: #decode?, }
+    } else if is_trailing_field(struct_attrs, field) {
+        quote! {
+            #id: if buf.is_empty() {
+                None
+            } else {
+                Some(#decode?)
+            },
+        }
+    } else if field.attrs.default {
+        quote! {
+            #id: if buf.is_empty() {
+                Default::default()
+            } else {
+                #decode?
+            },
+        }
+    } else {
+        quote! { #id: #decode?, }
+    }
 }
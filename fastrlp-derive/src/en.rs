@@ -1,160 +1,585 @@
+use crate::utils::*;
 use proc_macro2::TokenStream;
 use quote::quote;
 
-pub fn impl_encodable(ast: &syn::DeriveInput) -> TokenStream {
-    let body = if let syn::Data::Struct(s) = &ast.data {
-        s
-    } else {
-        panic!("#[derive(RlpEncodable)] is only defined for structs.");
+pub fn impl_encodable(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let body = match &ast.data {
+        syn::Data::Struct(s) => s,
+        syn::Data::Enum(e) => return impl_encodable_enum(ast, e),
+        syn::Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &ast.ident,
+                "#[derive(RlpEncodable)] is not defined for unions",
+            ))
+        }
     };
 
-    let length_stmts: Vec<_> = body
-        .fields
+    let struct_attrs = parse_struct_attrs(&ast.attrs)?;
+    let fields = parse_fields(&body.fields)?;
+    check_default_fields(&fields, &struct_attrs)?;
+
+    let encoded_fields: Vec<_> = fields.iter().filter(|field| !field.attrs.skip).collect();
+    let length_stmts: Vec<_> = encoded_fields
         .iter()
-        .enumerate()
-        .map(|(i, field)| encodable_length(i, field))
+        .map(|field| encodable_length(field, &struct_attrs, false))
+        .collect();
+    let cache_stmts: Vec<_> = encoded_fields
+        .iter()
+        .map(|field| encodable_length(field, &struct_attrs, true))
         .collect();
 
-    let stmts: Vec<_> = body
-        .fields
+    let stmts: Vec<_> = encoded_fields
         .iter()
-        .enumerate()
-        .map(|(i, field)| encodable_field(i, field))
+        .map(|field| encodable_field(field, &struct_attrs, false))
+        .collect();
+    let cached_stmts: Vec<_> = encoded_fields
+        .iter()
+        .map(|field| encodable_field(field, &struct_attrs, true))
         .collect();
-    let name = &ast.ident;
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
 
-    let impl_block = quote! {
-        trait E {
-            fn rlp_header(&self) -> fastrlp::Header;
+    let trailing_present: Vec<_> = fields
+        .iter()
+        .filter(|field| is_trailing_field(&struct_attrs, field))
+        .map(|field| {
+            let ident = &field.member;
+            quote! { self.#ident.is_some() }
+        })
+        .collect();
+    let trailing_check = if trailing_present.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            let present = [#(#trailing_present),*];
+            assert!(
+                present.windows(2).all(|w| w[0] || !w[1]),
+                "#[rlp(trailing)]: a None field must not be followed by a Some field"
+            );
         }
+    };
+    let name = &ast.ident;
+    let generics = add_bounds(
+        &ast.generics,
+        &struct_attrs.bound,
+        &fields,
+        quote! { fastrlp::Encodable },
+        quote! { fastrlp::EncodableFields },
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-        impl #impl_generics E for #name #ty_generics #where_clause {
-            fn rlp_header(&self) -> fastrlp::Header {
-                let mut rlp_head = fastrlp::Header { list: true, payload_length: 0 };
+    let impl_block = quote! {
+        impl #impl_generics fastrlp::EncodableFields for #name #ty_generics #where_clause {
+            fn fields_length(&self) -> usize {
+                #trailing_check
+                let mut payload_length = 0;
                 #(#length_stmts)*
-                rlp_head
+                payload_length
+            }
+            fn encode_fields(&self, out: &mut dyn fastrlp::BufMut) {
+                fastrlp::EncodableFields::encode_fields_to(self, out)
+            }
+            fn encode_fields_to<__B: fastrlp::BufMut + ?Sized>(&self, out: &mut __B) {
+                #(#stmts)*
+            }
+            fn cache_fields_lengths(&self, cache: &mut fastrlp::LengthCache) -> usize {
+                #trailing_check
+                let mut payload_length = 0;
+                #(#cache_stmts)*
+                payload_length
+            }
+            fn encode_fields_from_cache(
+                &self,
+                cache: &mut fastrlp::LengthCache,
+                out: &mut dyn fastrlp::BufMut,
+            ) {
+                #(#cached_stmts)*
             }
         }
 
         impl #impl_generics fastrlp::Encodable for #name #ty_generics #where_clause {
             fn length(&self) -> usize {
-                let rlp_head = E::rlp_header(self);
-                return fastrlp::length_of_length(rlp_head.payload_length) + rlp_head.payload_length;
+                let payload_length = fastrlp::EncodableFields::fields_length(self);
+                fastrlp::length_of_length(payload_length) + payload_length
             }
             fn encode(&self, out: &mut dyn fastrlp::BufMut) {
-                E::rlp_header(self).encode(out);
-                #(#stmts)*
+                fastrlp::Encodable::encode_to(self, out)
+            }
+            fn encode_to<__B: fastrlp::BufMut + ?Sized>(&self, out: &mut __B) {
+                fastrlp::Header {
+                    list: true,
+                    payload_length: fastrlp::EncodableFields::fields_length(self),
+                }
+                .encode(out);
+                fastrlp::EncodableFields::encode_fields_to(self, out);
+            }
+            fn cache_lengths(&self, cache: &mut fastrlp::LengthCache) -> usize {
+                let slot = cache.begin_list();
+                let payload_length = fastrlp::EncodableFields::cache_fields_lengths(self, cache);
+                cache.end_list(slot, payload_length)
+            }
+            fn encode_from_cache(
+                &self,
+                cache: &mut fastrlp::LengthCache,
+                out: &mut dyn fastrlp::BufMut,
+            ) {
+                cache.list_header().encode(out);
+                fastrlp::EncodableFields::encode_fields_from_cache(self, cache, out);
             }
         }
     };
 
-    quote! {
+    Ok(quote! {
         const _: () = {
             extern crate fastrlp;
             #impl_block
         };
-    }
+    })
 }
 
-pub fn impl_encodable_wrapper(ast: &syn::DeriveInput) -> TokenStream {
-    let body = if let syn::Data::Struct(s) = &ast.data {
-        s
+fn impl_encodable_enum(ast: &syn::DeriveInput, body: &syn::DataEnum) -> syn::Result<TokenStream> {
+    let enum_attrs = parse_enum_attrs(&ast.attrs)?;
+    let tagging = enum_attrs.tagging;
+    let tag_consts = variant_tag_consts(body);
+    let variant_fields = body
+        .variants
+        .iter()
+        .map(|variant| parse_fields(&variant.fields))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let mut length_arms = Vec::new();
+    let mut encode_arms = Vec::new();
+    let mut cache_arms = Vec::new();
+    let mut cached_encode_arms = Vec::new();
+    for (i, (variant, fields)) in body.variants.iter().zip(&variant_fields).enumerate() {
+        let ident = &variant.ident;
+        let tag = variant_tag_ident(i);
+        check_variant_attrs(variant, fields)?;
+        check_no_flatten(fields)?;
+        let encoded: Vec<_> = fields.iter().filter(|field| !field.attrs.skip).collect();
+        let members: Vec<_> = encoded.iter().map(|field| &field.member).collect();
+        let bindings = variant_bindings(&encoded);
+        let length_fns: Vec<_> = encoded
+            .iter()
+            .map(|field| length_fn(&field.attrs))
+            .collect();
+        let encode_stmts: Vec<_> = encoded
+            .iter()
+            .zip(&bindings)
+            .map(|(field, binding)| encode_stmt(&field.attrs, quote! { #binding }))
+            .collect();
+        let cache_lengths: Vec<_> = encoded
+            .iter()
+            .zip(&bindings)
+            .map(|(field, binding)| field_length(field, quote! { #binding }, true))
+            .collect();
+        let cached_encode_stmts: Vec<_> = encoded
+            .iter()
+            .zip(&bindings)
+            .map(|(field, binding)| field_encode(field, quote! { #binding }, true))
+            .collect();
+        let pattern = quote! { Self::#ident { #(#members: #bindings,)* .. } };
+
+        let (cache, cached_encode) = match tagging {
+            EnumTagging::List => (
+                quote! {
+                    let slot = cache.begin_list();
+                    let payload_length = fastrlp::Encodable::length(&#tag)
+                        #(+ #cache_lengths)*;
+                    cache.end_list(slot, payload_length)
+                },
+                quote! {
+                    cache.list_header().encode(out);
+                    fastrlp::Encodable::encode(&#tag, out);
+                    #(#cached_encode_stmts)*
+                },
+            ),
+            EnumTagging::TypeByte => (
+                quote! {
+                    let slot = cache.begin_list();
+                    let payload_length = 0 #(+ #cache_lengths)*;
+                    let length = 1 + cache.end_list(slot, payload_length);
+                    fastrlp::length_of_length(length) + length
+                },
+                quote! {
+                    let list_header = cache.list_header();
+                    let payload_length = list_header.payload_length;
+                    fastrlp::Header {
+                        list: false,
+                        payload_length: 1
+                            + fastrlp::length_of_length(payload_length)
+                            + payload_length,
+                    }
+                    .encode(out);
+                    out.put_u8(#tag as u8);
+                    list_header.encode(out);
+                    #(#cached_encode_stmts)*
+                },
+            ),
+            // Integer tags are not lists, so they keep the default methods.
+            EnumTagging::Integer => (quote! {}, quote! {}),
+        };
+        let (length, encode) = match tagging {
+            EnumTagging::List => (
+                quote! {
+                    let payload_length = fastrlp::Encodable::length(&#tag)
+                        #(+ #length_fns(#bindings))*;
+                    fastrlp::length_of_length(payload_length) + payload_length
+                },
+                quote! {
+                    fastrlp::Header {
+                        list: true,
+                        payload_length: fastrlp::Encodable::length(&#tag)
+                            #(+ #length_fns(#bindings))*,
+                    }
+                    .encode(out);
+                    fastrlp::Encodable::encode_to(&#tag, out);
+                    #(#encode_stmts)*
+                },
+            ),
+            EnumTagging::TypeByte => (
+                quote! {
+                    let payload_length = 0 #(+ #length_fns(#bindings))*;
+                    1 + fastrlp::length_of_length(payload_length) + payload_length
+                },
+                quote! {
+                    out.put_u8(#tag as u8);
+                    fastrlp::Header {
+                        list: true,
+                        payload_length: 0 #(+ #length_fns(#bindings))*,
+                    }
+                    .encode(out);
+                    #(#encode_stmts)*
+                },
+            ),
+            EnumTagging::Integer => {
+                check_unit_variant(variant)?;
+                (
+                    quote! { fastrlp::Encodable::length(&#tag) },
+                    quote! { fastrlp::Encodable::encode_to(&#tag, out); },
+                )
+            }
+        };
+        length_arms.push(quote! { #pattern => { #length } });
+        encode_arms.push(quote! { #pattern => { #encode } });
+        cache_arms.push(quote! { #pattern => { #cache } });
+        cached_encode_arms.push(quote! { #pattern => { #cached_encode } });
+    }
+
+    let tag_checks = if tagging == EnumTagging::TypeByte {
+        let checks = (0..body.variants.len()).map(|i| {
+            let tag = variant_tag_ident(i);
+            quote! { assert!(#tag < 0x80, "type byte tags must be below 0x80"); }
+        });
+        quote! { const _: () = { #(#checks)* }; }
     } else {
-        panic!("#[derive(RlpEncodableWrapper)] is only defined for structs.");
+        quote! {}
     };
 
-    let ident = {
-        let fields: Vec<_> = body.fields.iter().collect();
-        if fields.len() == 1 {
-            let field = fields.first().expect("fields.len() == 1; qed");
-            field_ident(0, field)
-        } else {
-            panic!("#[derive(RlpEncodableWrapper)] is only defined for structs with one field.")
+    let cache_methods = if tagging == EnumTagging::Integer {
+        quote! {}
+    } else {
+        quote! {
+            fn cache_lengths(&self, cache: &mut fastrlp::LengthCache) -> usize {
+                match self {
+                    #(#cache_arms)*
+                }
+            }
+            fn encode_from_cache(
+                &self,
+                cache: &mut fastrlp::LengthCache,
+                out: &mut dyn fastrlp::BufMut,
+            ) {
+                match self {
+                    #(#cached_encode_arms)*
+                }
+            }
         }
     };
 
     let name = &ast.ident;
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let generics = add_bounds(
+        &ast.generics,
+        &enum_attrs.bound,
+        variant_fields.iter().flatten(),
+        quote! { fastrlp::Encodable },
+        quote! { fastrlp::EncodableFields },
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // Type byte enums are encoded in EIP-2718 form wrapped in a byte string,
+    // so that they are a single item. The bare form has its own methods.
+    let (length, encode, encode_2718) = if tagging == EnumTagging::TypeByte {
+        (
+            quote! {
+                let length = self.length_2718();
+                fastrlp::length_of_length(length) + length
+            },
+            quote! {
+                fastrlp::Header {
+                    list: false,
+                    payload_length: self.length_2718(),
+                }
+                .encode(out);
+                self.encode_2718(out);
+            },
+            quote! {
+                impl #impl_generics #name #ty_generics #where_clause {
+                    /// Length of [`Self::encode_2718`] output.
+                    pub fn length_2718(&self) -> usize {
+                        match self {
+                            #(#length_arms)*
+                        }
+                    }
+
+                    /// Writes the EIP-2718 encoding, the type byte followed by
+                    /// the list of the variant's fields, without the byte
+                    /// string of `Encodable::encode`.
+                    pub fn encode_2718<__B: fastrlp::BufMut + ?Sized>(&self, out: &mut __B) {
+                        match self {
+                            #(#encode_arms)*
+                        }
+                    }
+                }
+            },
+        )
+    } else {
+        (
+            quote! {
+                match self {
+                    #(#length_arms)*
+                }
+            },
+            quote! {
+                match self {
+                    #(#encode_arms)*
+                }
+            },
+            quote! {},
+        )
+    };
 
     let impl_block = quote! {
+        #tag_consts
+        #tag_checks
+
         impl #impl_generics fastrlp::Encodable for #name #ty_generics #where_clause {
             fn length(&self) -> usize {
-                self.#ident.length()
+                #length
             }
             fn encode(&self, out: &mut dyn fastrlp::BufMut) {
-                self.#ident.encode(out)
+                fastrlp::Encodable::encode_to(self, out)
+            }
+            fn encode_to<__B: fastrlp::BufMut + ?Sized>(&self, out: &mut __B) {
+                #encode
             }
+            #cache_methods
         }
+
+        #encode_2718
     };
 
-    quote! {
+    Ok(quote! {
         const _: () = {
             extern crate fastrlp;
             #impl_block
         };
-    }
+    })
 }
 
-pub fn impl_max_encoded_len(ast: &syn::DeriveInput) -> TokenStream {
-    let body = if let syn::Data::Struct(s) = &ast.data {
-        s
-    } else {
-        panic!("#[derive(RlpEncodable)] is only defined for structs.");
+pub fn impl_encodable_wrapper(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let body = expect_struct(ast, "RlpEncodableWrapper")?;
+    let bound = parse_wrapper_attrs(&ast.attrs)?;
+    let field = wrapped_field(body, "RlpEncodableWrapper")?;
+    let ident = &field.member;
+    let length = field_length(&field, quote! { &self.#ident }, false);
+    let encode = field_encode(&field, quote! { &self.#ident }, false);
+    let cache_length = field_length(&field, quote! { &self.#ident }, true);
+    let cached_encode = field_encode(&field, quote! { &self.#ident }, true);
+
+    let name = &ast.ident;
+    let generics = add_bounds(
+        &ast.generics,
+        &bound,
+        [&field],
+        quote! { fastrlp::Encodable },
+        quote! { fastrlp::EncodableFields },
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let impl_block = quote! {
+        impl #impl_generics fastrlp::Encodable for #name #ty_generics #where_clause {
+            fn length(&self) -> usize {
+                #length
+            }
+            fn encode(&self, out: &mut dyn fastrlp::BufMut) {
+                fastrlp::Encodable::encode_to(self, out)
+            }
+            fn encode_to<__B: fastrlp::BufMut + ?Sized>(&self, out: &mut __B) {
+                #encode
+            }
+            fn cache_lengths(&self, cache: &mut fastrlp::LengthCache) -> usize {
+                #cache_length
+            }
+            fn encode_from_cache(
+                &self,
+                cache: &mut fastrlp::LengthCache,
+                out: &mut dyn fastrlp::BufMut,
+            ) {
+                #cached_encode
+            }
+        }
     };
 
-    let stmts: Vec<_> = body
-        .fields
+    Ok(quote! {
+        const _: () = {
+            extern crate fastrlp;
+            #impl_block
+        };
+    })
+}
+
+pub fn impl_max_encoded_len(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let body = expect_struct(ast, "RlpMaxEncodedLen")?;
+
+    let struct_attrs = parse_struct_attrs(&ast.attrs)?;
+    let fields = parse_fields(&body.fields)?;
+    let stmts: Vec<_> = fields
         .iter()
-        .enumerate()
-        .map(|(index, field)| encodable_max_length(index, field))
+        .filter(|field| !field.attrs.skip)
+        .map(|field| encodable_max_length(field, &struct_attrs))
         .collect();
     let name = &ast.ident;
+    let generics = add_bounds(
+        &ast.generics,
+        &struct_attrs.bound,
+        &fields,
+        quote! { fastrlp::MaxEncodedLenAssoc },
+        quote! { fastrlp::MaxEncodedFieldsLen },
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // Generic parameters can't be used in const generic arguments on stable,
+    // so generic types only get the associated constant.
+    let const_impl = if ast.generics.params.is_empty() {
+        quote! {
+            unsafe impl fastrlp::MaxEncodedLen<{ <#name as fastrlp::MaxEncodedLenAssoc>::LEN }> for #name {}
+        }
+    } else {
+        quote! {}
+    };
 
     let impl_block = quote! {
-        unsafe impl fastrlp::MaxEncodedLen<{ fastrlp::const_add(fastrlp::length_of_length(#(#stmts)*), #(#stmts)*) }> for #name {}
-        unsafe impl fastrlp::MaxEncodedLenAssoc for #name {
-            const LEN: usize = { fastrlp::const_add(fastrlp::length_of_length(#(#stmts)*), { #(#stmts)* }) };
+        unsafe impl #impl_generics fastrlp::MaxEncodedFieldsLen for #name #ty_generics #where_clause {
+            const FIELDS_LEN: usize = 0 #(+ #stmts)*;
+        }
+        unsafe impl #impl_generics fastrlp::MaxEncodedLenAssoc for #name #ty_generics #where_clause {
+            const LEN: usize = {
+                let fields_len = <Self as fastrlp::MaxEncodedFieldsLen>::FIELDS_LEN;
+                fastrlp::const_add(fastrlp::length_of_length(fields_len), fields_len)
+            };
         }
+        #const_impl
     };
 
-    quote! {
+    Ok(quote! {
         const _: () = {
             extern crate fastrlp;
             #impl_block
         };
-    }
+    })
 }
 
-fn field_ident(index: usize, field: &syn::Field) -> TokenStream {
-    if let Some(ident) = &field.ident {
-        quote! { #ident }
-    } else {
-        let index = syn::Index::from(index);
-        quote! { #index }
+fn encodable_length(field: &RlpField, struct_attrs: &StructAttrs, cached: bool) -> TokenStream {
+    let ident = &field.member;
+
+    if field.attrs.flatten {
+        return if cached {
+            quote! {
+                payload_length +=
+                    fastrlp::EncodableFields::cache_fields_lengths(&self.#ident, cache);
+            }
+        } else {
+            quote! {
+                payload_length += fastrlp::EncodableFields::fields_length(&self.#ident);
+            }
+        };
     }
-}
 
-fn encodable_length(index: usize, field: &syn::Field) -> TokenStream {
-    let ident = field_ident(index, field);
+    if is_trailing_field(struct_attrs, field) {
+        let length = field_length(field, quote! { val }, cached);
+        return quote! {
+            if let Some(val) = &self.#ident {
+                payload_length += #length;
+            }
+        };
+    }
 
-    quote! { rlp_head.payload_length += fastrlp::Encodable::length(&self.#ident); }
+    let length = field_length(field, quote! { &self.#ident }, cached);
+    quote! { payload_length += #length; }
 }
 
-fn encodable_max_length(index: usize, field: &syn::Field) -> TokenStream {
-    let fieldtype = &field.ty;
+fn encodable_max_length(field: &RlpField, struct_attrs: &StructAttrs) -> TokenStream {
+    if let Some(path) = &field.attrs.with {
+        return quote! { #path::LEN };
+    }
 
-    if index == 0 {
-        quote! { <#fieldtype as fastrlp::MaxEncodedLenAssoc>::LEN }
-    } else {
-        quote! { + <#fieldtype as fastrlp::MaxEncodedLenAssoc>::LEN }
+    if field.attrs.flatten {
+        let fieldtype = &field.field.ty;
+        return quote! { <#fieldtype as fastrlp::MaxEncodedFieldsLen>::FIELDS_LEN };
     }
+
+    // A trailing field is at its longest when present.
+    let fieldtype = if is_trailing_field(struct_attrs, field) {
+        option_inner(&field.field.ty).expect("trailing fields are options; qed")
+    } else {
+        &field.field.ty
+    };
+
+    quote! { <#fieldtype as fastrlp::MaxEncodedLenAssoc>::LEN }
 }
 
-fn encodable_field(index: usize, field: &syn::Field) -> TokenStream {
-    let ident = field_ident(index, field);
+fn encodable_field(field: &RlpField, struct_attrs: &StructAttrs, cached: bool) -> TokenStream {
+    let ident = &field.member;
 
-    let id = quote! { self.#ident };
+    if field.attrs.flatten {
+        return if cached {
+            quote! {
+                fastrlp::EncodableFields::encode_fields_from_cache(&self.#ident, cache, out);
+            }
+        } else {
+            quote! { fastrlp::EncodableFields::encode_fields_to(&self.#ident, out); }
+        };
+    }
+
+    if is_trailing_field(struct_attrs, field) {
+        let encode = field_encode(field, quote! { val }, cached);
+        return quote! {
+            if let Some(val) = &self.#ident {
+                #encode
+            }
+        };
+    }
+
+    field_encode(field, quote! { &self.#ident }, cached)
+}
+
+/// Encoded length of `value`, a reference to `field`. With `cached`, the
+/// lengths of its lists are recorded in `cache`.
+fn field_length(field: &RlpField, value: TokenStream, cached: bool) -> TokenStream {
+    if cached && field.attrs.with.is_none() {
+        return quote! { fastrlp::Encodable::cache_lengths(#value, cache) };
+    }
+    let length = length_fn(&field.attrs);
+    quote! { #length(#value) }
+}
 
-    quote! { fastrlp::Encodable::encode(&#id, out); }
+/// Statement encoding `value`, a reference to `field`. With `cached`, `out` is
+/// a trait object and the list headers are read from `cache`.
+fn field_encode(field: &RlpField, value: TokenStream, cached: bool) -> TokenStream {
+    if !cached {
+        return encode_stmt(&field.attrs, value);
+    }
+    match &field.attrs.with {
+        Some(path) => quote! { #path::encode(#value, out); },
+        None => quote! { fastrlp::Encodable::encode_from_cache(#value, cache, out); },
+    }
 }
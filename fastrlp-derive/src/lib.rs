@@ -2,52 +2,146 @@
 //!
 //! For example of usage see `./tests/rlp.rs`.
 //!
-//! This library also supports up to 1 `#[rlp(default)]` in a struct,
-//! which is similar to [`#[serde(default)]`](https://serde.rs/field-attrs.html#default)
-//! with the caveat that we use the `Default` value if
-//! the field deserialization fails, as we don't serialize field
-//! names and there is no way to tell if it is present or not.
+//! This library also supports `#[rlp(default)]` fields, which are similar to
+//! [`#[serde(default)]`](https://serde.rs/field-attrs.html#default): when the
+//! encoded list ends before such a field, it is filled with its `Default`
+//! value. As field names are not serialized, presence can only be told by
+//! position, so `#[rlp(default)]` fields must be the last fields of the
+//! struct. They are always written when encoding.
+//!
+//! Structs marked `#[rlp(trailing)]` may end with `Option` fields, which are
+//! only written when they are `Some`. Decoding fills them with `None` once
+//! the list is exhausted. Such fields must come after all other fields,
+//! including `#[rlp(default)]` ones. Encoding a `None` field that is followed
+//! by a `Some` field panics, as the result could not be decoded back.
+//!
+//! Decoding fails when a list has more elements than the struct has fields,
+//! unless the struct is marked `#[rlp(allow_trailing)]`. Extra elements are
+//! then skipped, as devp2p (EIP-8) requires for forward compatibility.
+//!
+//! Fields marked `#[rlp(skip)]` are left out of the encoding, and of the
+//! length computed by `RlpMaxEncodedLen`. Decoding fills them with their
+//! `Default` value. This is useful for cached values, such as a memoized
+//! hash, kept next to the encoded fields.
+//!
+//! `#[rlp(with = "path")]` encodes and decodes a field with the functions of
+//! the module at `path` instead of its `Encodable` and `Decodable` impls:
+//!
+//! ```ignore
+//! fn encode(value: &T, out: &mut dyn BufMut);
+//! fn length(value: &T) -> usize;
+//! fn decode(buf: &mut &[u8]) -> Result<T, DecodeError>;
+//! ```
+//!
+//! `RlpMaxEncodedLen` takes the field's maximum length from the module's
+//! `LEN: usize` constant.
+//!
+//! `#[rlp(bytes)]` encodes a `Vec<u8>` field, or another `AsRef<[u8]>`
+//! container that implements `From<Vec<u8>>`, as an RLP string like `Bytes`,
+//! rather than as a list of integers. It is a shorthand for
+//! `#[rlp(with = "fastrlp::byte_vec")]`.
+//!
+//! `#[rlp(flatten)]` inlines the fields of a struct deriving the same traits
+//! into the parent's list instead of encoding it as a nested list. The
+//! flattened struct's own `#[rlp(default)]` and trailing fields are only
+//! optional if it is flattened as the last field.
+//!
+//! Generic types get trait bounds inferred the way serde does: every type
+//! parameter used by an encoded field must implement the derived trait, so
+//! there is no need to put `T: Encodable` on the type definition. Skipped
+//! fields and `#[rlp(with)]` fields add no bounds. `#[rlp(bound = "...")]`
+//! replaces the inferred where predicates, either for the whole type when put
+//! on the container, or for one field. `RlpMaxEncodedLen` only implements
+//! `MaxEncodedLenAssoc` for generic types, as `MaxEncodedLen<LEN>` would need
+//! generic parameters in a const argument.
+//!
+//! `RlpEncodable` implements `Encodable::encode_to`, and the methods used by
+//! `fastrlp::encode_cached` to compute the length of each nested list once.
+//!
+//! `RlpDecodable` also implements `Decodable::decode_with_context`, whose
+//! errors name the field that failed to decode, as in
+//! `Block.transactions[17].access_list[2].storage_keys`.
+//!
+//! `RlpDecodable` also implements `Decodable::decode_bytes` for structs, so
+//! that their `Bytes` fields are views of the decoded `Bytes` buffer instead
+//! of copies. Flattened and `#[rlp(with)]` fields are decoded from a slice,
+//! copying whatever they hold.
+//!
+//! Fields marked `#[rlp(borrow)]` are decoded with `BorrowDecodable`, so that
+//! fields such as `&'a [u8]`, `&'a str`, `&'a [u8; N]` or `Vec<TxRef<'a>>`
+//! borrow from the decoded buffer rather than being copied. `RlpDecodable` on
+//! a struct with such fields implements `BorrowDecodable<'de>` instead of
+//! `Decodable`. Other derived types implement both.
+//!
+//! `RlpEncodable` and `RlpDecodable` can also be derived for enums. Every
+//! variant is identified by a tag, which is its discriminant (explicit or
+//! implicit). The way the tag is written is chosen with `#[rlp(tag = "...")]`
+//! on the enum:
+//!
+//! - `"list"` (default): the variant is encoded as a list whose first element
+//!   is the tag, followed by the variant's fields.
+//! - `"type_byte"`: [EIP-2718](https://eips.ethereum.org/EIPS/eip-2718) style,
+//!   a byte string holding a single tag byte followed by the list of the
+//!   variant's fields, as typed transactions are written in lists. Tags must
+//!   be below `0x80`. The bare `tag || list` form is written and read by the
+//!   generated `encode_2718`, `length_2718` and `decode_2718` methods.
+//! - `"integer"`: unit-only enums are encoded as the tag integer itself.
+//!
+//! Decoding a tag that matches no variant fails with
+//! `DecodeError::UnknownTag`.
+//!
+//! `RlpEncodableWrapper` and `RlpDecodableWrapper` encode a struct with a
+//! single field as that field. The field may be marked `#[rlp(bytes)]` or
+//! `#[rlp(with = "path")]`, and the struct `#[rlp(bound = "...")]`; the other
+//! attributes are rejected.
 
 extern crate proc_macro;
 
 mod de;
 mod en;
+mod utils;
 
 use de::*;
 use en::*;
 use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
 
 #[proc_macro_derive(RlpEncodable, attributes(rlp))]
 pub fn encodable(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
-    let gen = impl_encodable(&ast);
-    gen.into()
+    let ast = parse_macro_input!(input as DeriveInput);
+    impl_encodable(&ast)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
 
 #[proc_macro_derive(RlpEncodableWrapper, attributes(rlp))]
 pub fn encodable_wrapper(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
-    let gen = impl_encodable_wrapper(&ast);
-    gen.into()
+    let ast = parse_macro_input!(input as DeriveInput);
+    impl_encodable_wrapper(&ast)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
 
 #[proc_macro_derive(RlpMaxEncodedLen, attributes(rlp))]
 pub fn max_encoded_len(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
-    let gen = impl_max_encoded_len(&ast);
-    gen.into()
+    let ast = parse_macro_input!(input as DeriveInput);
+    impl_max_encoded_len(&ast)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
 
 #[proc_macro_derive(RlpDecodable, attributes(rlp))]
 pub fn decodable(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
-    let gen = impl_decodable(&ast);
-    gen.into()
+    let ast = parse_macro_input!(input as DeriveInput);
+    impl_decodable(&ast)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
 
 #[proc_macro_derive(RlpDecodableWrapper, attributes(rlp))]
 pub fn decodable_wrapper(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
-    let gen = impl_decodable_wrapper(&ast);
-    gen.into()
+    let ast = parse_macro_input!(input as DeriveInput);
+    impl_decodable_wrapper(&ast)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
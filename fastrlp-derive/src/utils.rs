@@ -0,0 +1,537 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+#[derive(Default)]
+pub struct FieldAttrs {
+    pub default: bool,
+    pub skip: bool,
+    pub flatten: bool,
+    pub borrow: bool,
+    pub with: Option<syn::Path>,
+    pub bound: Option<Vec<syn::WherePredicate>>,
+}
+
+/// Nested metas of all `#[rlp(...)]` attributes in `attrs`.
+fn rlp_metas(attrs: &[syn::Attribute]) -> syn::Result<Vec<syn::NestedMeta>> {
+    let mut metas = Vec::new();
+    for attr in attrs {
+        if !attr.path.is_ident("rlp") {
+            continue;
+        }
+        match attr.parse_meta()? {
+            syn::Meta::List(list) => metas.extend(list.nested),
+            meta => return Err(syn::Error::new_spanned(meta, "expected #[rlp(...)]")),
+        }
+    }
+    Ok(metas)
+}
+
+pub fn parse_field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs::default();
+    let mut bytes = None;
+    for nested in rlp_metas(&field.attrs)? {
+        match &nested {
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("default") => {
+                attrs.default = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("skip") => {
+                attrs.skip = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("flatten") => {
+                attrs.flatten = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("borrow") => {
+                attrs.borrow = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("bytes") => {
+                bytes = Some(path.clone());
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("with") => {
+                attrs.with = Some(match &nv.lit {
+                    syn::Lit::Str(s) => s.parse()?,
+                    lit => {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "expected #[rlp(with = \"path::to::module\")]",
+                        ))
+                    }
+                });
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("bound") => {
+                attrs.bound = Some(parse_bound(&nv.lit)?);
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    nested,
+                    "unknown field attribute, expected one of `default`, `skip`, `flatten`, `borrow`, `bytes`, `with`, `bound`",
+                ))
+            }
+        }
+    }
+    // `#[rlp(bytes)]` is a shorthand for the crate's byte string codec.
+    if let Some(path) = bytes {
+        if attrs.with.is_some() {
+            return Err(syn::Error::new_spanned(
+                path,
+                "#[rlp(bytes)] can't be combined with `with`",
+            ));
+        }
+        attrs.with = Some(syn::parse_quote! { fastrlp::byte_vec });
+    }
+    if attrs.flatten && (attrs.default || attrs.with.is_some()) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "#[rlp(flatten)] can't be combined with `default`, `bytes` or `with`",
+        ));
+    }
+    if attrs.borrow && attrs.with.is_some() {
+        return Err(syn::Error::new_spanned(
+            field,
+            "#[rlp(borrow)] can't be combined with `bytes` or `with`",
+        ));
+    }
+    Ok(attrs)
+}
+
+/// A struct or variant field together with its `#[rlp(...)]` attributes.
+pub struct RlpField<'a> {
+    pub member: TokenStream,
+    pub field: &'a syn::Field,
+    pub attrs: FieldAttrs,
+}
+
+pub fn parse_fields(fields: &syn::Fields) -> syn::Result<Vec<RlpField<'_>>> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            Ok(RlpField {
+                member: field_ident(i, field),
+                field,
+                attrs: parse_field_attrs(field)?,
+            })
+        })
+        .collect()
+}
+
+#[derive(Default)]
+pub struct StructAttrs {
+    pub trailing: bool,
+    pub allow_trailing: bool,
+    pub bound: Option<Vec<syn::WherePredicate>>,
+}
+
+pub fn parse_struct_attrs(attrs: &[syn::Attribute]) -> syn::Result<StructAttrs> {
+    let mut struct_attrs = StructAttrs::default();
+    for nested in rlp_metas(attrs)? {
+        match &nested {
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("trailing") => {
+                struct_attrs.trailing = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("allow_trailing") => {
+                struct_attrs.allow_trailing = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("bound") => {
+                struct_attrs.bound = Some(parse_bound(&nv.lit)?);
+            }
+            _ => return Err(syn::Error::new_spanned(
+                nested,
+                "unknown struct attribute, expected one of `trailing`, `allow_trailing`, `bound`",
+            )),
+        }
+    }
+    Ok(struct_attrs)
+}
+
+/// Returns `T` if `ty` is spelled `Option<T>`.
+pub fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first() {
+        Some(syn::GenericArgument::Type(inner)) if args.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
+
+/// Statement encoding `value`, a reference to a field, into `out`, a
+/// `&mut __B` where `__B: BufMut + ?Sized`: with `encode` of its
+/// `#[rlp(with)]` module if there is one, `Encodable::encode_to` otherwise.
+pub fn encode_stmt(attrs: &FieldAttrs, value: TokenStream) -> TokenStream {
+    match &attrs.with {
+        // `with` modules take a trait object, which `&mut __B` can be made
+        // into even if `__B` is unsized.
+        Some(path) => quote! { #path::encode(#value, &mut &mut *out); },
+        None => quote! { fastrlp::Encodable::encode_to(#value, out); },
+    }
+}
+
+/// Function computing the encoded length of a field, as in [`encode_stmt`].
+pub fn length_fn(attrs: &FieldAttrs) -> TokenStream {
+    match &attrs.with {
+        Some(path) => quote! { #path::length },
+        None => quote! { fastrlp::Encodable::length },
+    }
+}
+
+/// Function decoding a field, as in [`encode_stmt`]. `#[rlp(borrow)]` fields
+/// use `BorrowDecodable::borrow_decode`.
+pub fn decode_fn(attrs: &FieldAttrs) -> TokenStream {
+    match &attrs.with {
+        Some(path) => quote! { #path::decode },
+        None if attrs.borrow => quote! { fastrlp::BorrowDecodable::borrow_decode },
+        None => quote! { fastrlp::Decodable::decode },
+    }
+}
+
+/// Adds the `'de` lifetime of the decoded buffer to `generics`. Types that
+/// borrow from the buffer need it to outlive all of their own lifetimes.
+pub fn add_de_lifetime(generics: &syn::Generics, borrowed: bool) -> syn::Generics {
+    let mut generics = generics.clone();
+    let mut de: syn::LifetimeDef = syn::parse_quote! { 'de };
+    de.bounds = generics
+        .lifetimes()
+        .filter(|_| borrowed)
+        .map(|param| param.lifetime.clone())
+        .collect();
+    de.colon_token = Some(Default::default()).filter(|_| !de.bounds.is_empty());
+    generics.params.insert(0, syn::GenericParam::Lifetime(de));
+    generics
+}
+
+/// Whether `field` is an `Option` field of a `#[rlp(trailing)]` struct, which
+/// is left out of the list when `None`.
+pub fn is_trailing_field(struct_attrs: &StructAttrs, field: &RlpField) -> bool {
+    struct_attrs.trailing && !field.attrs.flatten && option_inner(&field.field.ty).is_some()
+}
+
+/// Fields marked `#[rlp(default)]` and trailing `Option` fields may be absent
+/// from the encoded list, so they are only allowed as a suffix of the struct's
+/// fields. Trailing fields come last, as default fields are always written.
+/// Skipped fields are not encoded, so they may appear anywhere.
+pub fn check_default_fields(fields: &[RlpField], struct_attrs: &StructAttrs) -> syn::Result<()> {
+    let mut seen_default = false;
+    let mut seen_trailing = false;
+    for field in fields {
+        if field.attrs.skip {
+            continue;
+        }
+        if is_trailing_field(struct_attrs, field) {
+            seen_trailing = true;
+        } else if seen_trailing {
+            return Err(syn::Error::new_spanned(
+                field.field,
+                "#[rlp(trailing)] Option fields must be the last fields of the struct",
+            ));
+        } else if field.attrs.default {
+            seen_default = true;
+        } else if seen_default {
+            return Err(syn::Error::new_spanned(
+                field.field,
+                "#[rlp(default)] fields must be the last fields of the struct",
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn field_ident(index: usize, field: &syn::Field) -> TokenStream {
+    if let Some(ident) = &field.ident {
+        quote! { #ident }
+    } else {
+        let index = syn::Index::from(index);
+        quote! { #index }
+    }
+}
+
+/// How enum variants are told apart in the encoding.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum EnumTagging {
+    /// The tag is the first element of the variant's list.
+    List,
+    /// EIP-2718 style: a single tag byte followed by the variant's list.
+    TypeByte,
+    /// Unit-only enums encoded as the tag integer itself.
+    Integer,
+}
+
+pub struct EnumAttrs {
+    pub tagging: EnumTagging,
+    pub bound: Option<Vec<syn::WherePredicate>>,
+}
+
+pub fn parse_enum_attrs(attrs: &[syn::Attribute]) -> syn::Result<EnumAttrs> {
+    let mut enum_attrs = EnumAttrs {
+        tagging: EnumTagging::List,
+        bound: None,
+    };
+    for nested in rlp_metas(attrs)? {
+        match &nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("tag") => {
+                enum_attrs.tagging = match &nv.lit {
+                    syn::Lit::Str(s) if s.value() == "list" => EnumTagging::List,
+                    syn::Lit::Str(s) if s.value() == "type_byte" => EnumTagging::TypeByte,
+                    syn::Lit::Str(s) if s.value() == "integer" => EnumTagging::Integer,
+                    lit => {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "expected #[rlp(tag = \"list\" | \"type_byte\" | \"integer\")]",
+                        ))
+                    }
+                };
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("bound") => {
+                enum_attrs.bound = Some(parse_bound(&nv.lit)?);
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    nested,
+                    "unknown enum attribute, expected one of `tag`, `bound`",
+                ))
+            }
+        }
+    }
+    Ok(enum_attrs)
+}
+
+/// Name of the constant holding the tag of the variant at `index`.
+pub fn variant_tag_ident(index: usize) -> syn::Ident {
+    quote::format_ident!("RLP_TAG_{}", index)
+}
+
+/// Defines a `u64` constant for every variant tag. Tags follow Rust's
+/// discriminant rules: an explicit discriminant is used as is, otherwise the
+/// tag is one more than the previous variant's, starting from zero.
+pub fn variant_tag_consts(data: &syn::DataEnum) -> TokenStream {
+    let consts = data.variants.iter().enumerate().map(|(i, variant)| {
+        let ident = variant_tag_ident(i);
+        let value = if let Some((_, expr)) = &variant.discriminant {
+            quote! { #expr }
+        } else if i == 0 {
+            quote! { 0 }
+        } else {
+            let prev = variant_tag_ident(i - 1);
+            quote! { #prev + 1 }
+        };
+        quote! { const #ident: u64 = #value; }
+    });
+    quote! { #(#consts)* }
+}
+
+/// Names the encoded fields of a variant are bound to when matching it.
+/// Patterns use the braced form, which also works for tuple and unit
+/// variants: `Self::V { 0: _0, 1: _1, .. }`.
+pub fn variant_bindings(fields: &[&RlpField]) -> Vec<syn::Ident> {
+    (0..fields.len())
+        .map(|i| quote::format_ident!("_{}", i))
+        .collect()
+}
+
+/// Struct of a derive input that must be a struct.
+pub fn expect_struct<'a>(
+    ast: &'a syn::DeriveInput,
+    derive: &str,
+) -> syn::Result<&'a syn::DataStruct> {
+    if let syn::Data::Struct(s) = &ast.data {
+        Ok(s)
+    } else {
+        Err(syn::Error::new_spanned(
+            &ast.ident,
+            format!("#[derive({derive})] is only defined for structs"),
+        ))
+    }
+}
+
+/// The only field of a wrapper struct, which is encoded as is: it can't be
+/// skipped, flattened, defaulted or borrowed.
+pub fn wrapped_field<'a>(body: &'a syn::DataStruct, derive: &str) -> syn::Result<RlpField<'a>> {
+    let mut fields = parse_fields(&body.fields)?.into_iter();
+    let field = match (fields.next(), fields.next()) {
+        (Some(field), None) => field,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &body.fields,
+                format!("#[derive({derive})] is only defined for structs with one field"),
+            ))
+        }
+    };
+    let attrs = &field.attrs;
+    let unsupported = [
+        (attrs.skip, "skip"),
+        (attrs.flatten, "flatten"),
+        (attrs.default, "default"),
+        (attrs.borrow, "borrow"),
+    ];
+    match unsupported.iter().find(|(set, _)| *set) {
+        Some((_, attr)) => Err(syn::Error::new_spanned(
+            field.field,
+            format!("#[rlp({attr})] is not defined for the field of #[derive({derive})]"),
+        )),
+        None => Ok(field),
+    }
+}
+
+/// Parses the attributes of a wrapper struct, which only take a `bound`.
+pub fn parse_wrapper_attrs(
+    attrs: &[syn::Attribute],
+) -> syn::Result<Option<Vec<syn::WherePredicate>>> {
+    let mut bound = None;
+    for nested in rlp_metas(attrs)? {
+        match &nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("bound") => {
+                bound = Some(parse_bound(&nv.lit)?);
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    nested,
+                    "unknown wrapper attribute, expected `bound`",
+                ))
+            }
+        }
+    }
+    Ok(bound)
+}
+
+/// Parses the where predicates of `#[rlp(bound = "...")]`.
+fn parse_bound(lit: &syn::Lit) -> syn::Result<Vec<syn::WherePredicate>> {
+    if let syn::Lit::Str(s) = lit {
+        let predicates = s
+            .parse_with(
+                syn::punctuated::Punctuated::<syn::WherePredicate, syn::Token![,]>::parse_terminated,
+            )
+            .map_err(|err| syn::Error::new_spanned(s, err))?;
+        Ok(predicates.into_iter().collect())
+    } else {
+        Err(syn::Error::new_spanned(
+            lit,
+            "expected #[rlp(bound = \"T: Trait, ...\")]",
+        ))
+    }
+}
+
+fn is_ident_type(ty: &syn::Type, ident: &syn::Ident) -> bool {
+    matches!(ty, syn::Type::Path(path) if path.qself.is_none() && path.path.is_ident(ident))
+}
+
+fn mentions_ident(tokens: TokenStream, ident: &syn::Ident) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Ident(i) => i == *ident,
+        proc_macro2::TokenTree::Group(g) => mentions_ident(g.stream(), ident),
+        _ => false,
+    })
+}
+
+/// Generics of a derived impl of `bound`. Like serde, every type parameter
+/// used by an encoded field gets a `T: bound` predicate, unless the container
+/// or the field replaces the inferred predicates with `#[rlp(bound = "...")]`.
+/// Fields with `#[rlp(with)]` infer no bounds, as their module does the work.
+/// A type parameter that is itself an `#[rlp(flatten)]` field gets
+/// `flatten_bound` instead.
+pub fn add_bounds<'a>(
+    generics: &syn::Generics,
+    container_bound: &Option<Vec<syn::WherePredicate>>,
+    fields: impl IntoIterator<Item = &'a RlpField<'a>>,
+    bound: TokenStream,
+    flatten_bound: TokenStream,
+) -> syn::Generics {
+    let mut generics = generics.clone();
+    let predicates = if let Some(predicates) = container_bound {
+        predicates.clone()
+    } else {
+        let mut predicates = Vec::new();
+        let mut used = Vec::new();
+        let mut flattened = Vec::new();
+        for field in fields.into_iter().filter(|field| !field.attrs.skip) {
+            if let Some(field_bound) = &field.attrs.bound {
+                predicates.extend(field_bound.iter().cloned());
+            } else if field.attrs.with.is_none() {
+                let ty = &field.field.ty;
+                let param = generics
+                    .type_params()
+                    .find(|param| is_ident_type(ty, &param.ident));
+                match param {
+                    Some(param) if field.attrs.flatten => flattened.push(param.ident.clone()),
+                    _ => used.extend(
+                        generics
+                            .type_params()
+                            .map(|param| &param.ident)
+                            .filter(|ident| mentions_ident(quote! { #ty }, ident))
+                            .cloned(),
+                    ),
+                }
+            }
+        }
+        for ident in generics.type_params().map(|param| &param.ident) {
+            if used.contains(ident) {
+                predicates.push(syn::parse_quote! { #ident: #bound });
+            }
+            if flattened.contains(ident) {
+                predicates.push(syn::parse_quote! { #ident: #flatten_bound });
+            }
+        }
+        predicates
+    };
+    generics.make_where_clause().predicates.extend(predicates);
+    generics
+}
+
+/// Only struct fields can be flattened, as the enum derives write each
+/// variant's fields directly.
+pub fn check_no_flatten(fields: &[RlpField]) -> syn::Result<()> {
+    match fields.iter().find(|field| field.attrs.flatten) {
+        Some(field) => Err(syn::Error::new_spanned(
+            field.field,
+            "#[rlp(flatten)] is only defined for struct fields",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Only struct fields can borrow from the buffer, as derived enums and
+/// wrappers implement `Decodable`.
+pub fn check_no_borrow<'a>(fields: impl IntoIterator<Item = &'a RlpField<'a>>) -> syn::Result<()> {
+    match fields.into_iter().find(|field| field.attrs.borrow) {
+        Some(field) => Err(syn::Error::new_spanned(
+            field.field,
+            "#[rlp(borrow)] is only defined for struct fields",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Variants take no attributes, and their fields can't be defaulted: a
+/// variant's list always holds all of its fields.
+pub fn check_variant_attrs(variant: &syn::Variant, fields: &[RlpField]) -> syn::Result<()> {
+    if let Some(attr) = variant.attrs.iter().find(|attr| attr.path.is_ident("rlp")) {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "#[rlp] attributes are not defined for enum variants",
+        ));
+    }
+    match fields.iter().find(|field| field.attrs.default) {
+        Some(field) => Err(syn::Error::new_spanned(
+            field.field,
+            "#[rlp(default)] is only defined for struct fields",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// `#[rlp(tag = "integer")]` enums may only have unit variants.
+pub fn check_unit_variant(variant: &syn::Variant) -> syn::Result<()> {
+    if variant.fields.is_empty() {
+        Ok(())
+    } else {
+        Err(syn::Error::new_spanned(
+            &variant.fields,
+            "#[rlp(tag = \"integer\")] is only defined for enums with unit variants",
+        ))
+    }
+}
@@ -0,0 +1,9 @@
+use fastrlp::RlpEncodable;
+
+#[derive(RlpEncodable)]
+#[rlp(bound = "T:: Encodable")]
+struct S<T> {
+    a: T,
+}
+
+fn main() {}
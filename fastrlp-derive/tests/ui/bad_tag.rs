@@ -0,0 +1,9 @@
+use fastrlp::RlpEncodable;
+
+#[derive(RlpEncodable)]
+#[rlp(tag = "byte")]
+enum E {
+    A,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use fastrlp::RlpEncodable;
+
+#[derive(RlpEncodable)]
+struct S {
+    #[rlp(with = 1)]
+    a: u64,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use fastrlp::RlpDecodable;
+
+#[derive(RlpDecodable)]
+struct Tx {
+    nonce: u64,
+    #[rlp(borrow)]
+    input: Vec<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use fastrlp::RlpEncodable;
+
+mod codec {}
+
+#[derive(RlpEncodable)]
+struct S {
+    #[rlp(bytes, with = "codec")]
+    a: Vec<u8>,
+}
+
+fn main() {}
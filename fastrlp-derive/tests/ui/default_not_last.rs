@@ -0,0 +1,10 @@
+use fastrlp::RlpDecodable;
+
+#[derive(RlpDecodable)]
+struct S {
+    #[rlp(default)]
+    a: u64,
+    b: u64,
+}
+
+fn main() {}
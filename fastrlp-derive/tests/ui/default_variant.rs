@@ -0,0 +1,8 @@
+use fastrlp::RlpDecodable;
+
+#[derive(RlpDecodable)]
+enum E {
+    A { x: u64, #[rlp(default)] y: u64 },
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use fastrlp::RlpEncodable;
+
+#[derive(RlpEncodable)]
+struct Signature {
+    v: u64,
+}
+
+#[derive(RlpEncodable)]
+enum E {
+    A(#[rlp(flatten)] Signature),
+}
+
+fn main() {}
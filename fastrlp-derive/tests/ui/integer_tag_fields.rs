@@ -0,0 +1,10 @@
+use fastrlp::RlpDecodable;
+
+#[derive(RlpDecodable)]
+#[rlp(tag = "integer")]
+enum E {
+    A,
+    B(u64),
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use fastrlp::{RlpEncodable, RlpMaxEncodedLen};
+
+#[derive(RlpEncodable, RlpMaxEncodedLen)]
+enum E {
+    A(u64),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use fastrlp::RlpEncodable;
+
+#[derive(RlpEncodable)]
+#[rlp(trailing)]
+struct S {
+    a: Option<u64>,
+    b: u64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use fastrlp::RlpEncodable;
+
+#[derive(RlpEncodable)]
+union U {
+    a: u8,
+    b: u64,
+}
+
+fn main() {}
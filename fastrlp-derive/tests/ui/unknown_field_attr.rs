@@ -0,0 +1,10 @@
+use fastrlp::RlpEncodable;
+
+#[derive(RlpEncodable)]
+struct S {
+    a: u64,
+    #[rlp(defualt)]
+    b: u64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use fastrlp::RlpDecodable;
+
+#[derive(RlpDecodable)]
+#[rlp(trailing, tag = "list")]
+struct S {
+    a: u64,
+}
+
+fn main() {}
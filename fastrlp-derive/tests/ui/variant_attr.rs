@@ -0,0 +1,9 @@
+use fastrlp::RlpEncodable;
+
+#[derive(RlpEncodable)]
+enum E {
+    #[rlp(trailing)]
+    A { x: u64, y: Option<u64> },
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use fastrlp::{RlpDecodableWrapper, RlpEncodableWrapper};
+
+#[derive(RlpEncodableWrapper)]
+struct Skipped(#[rlp(skip)] u64);
+
+#[derive(RlpDecodableWrapper)]
+struct Defaulted(#[rlp(default)] u64);
+
+#[derive(RlpEncodableWrapper)]
+#[rlp(trailing)]
+struct Trailing(Option<u64>);
+
+#[derive(RlpDecodableWrapper)]
+#[rlp(allow_trailing)]
+struct AllowTrailing(u64);
+
+fn main() {}
@@ -0,0 +1,8 @@
+use fastrlp::RlpEncodableWrapper;
+
+#[derive(RlpEncodableWrapper)]
+enum E {
+    A(u64),
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use fastrlp::RlpDecodableWrapper;
+
+#[derive(RlpDecodableWrapper)]
+struct W(u64, u64);
+
+fn main() {}
@@ -0,0 +1,56 @@
+//! Encoding of byte vectors as RLP strings.
+//!
+//! `Vec<u8>` implements [`Encodable`] through the generic `Vec<T>` impl,
+//! which writes a list of single-byte integers. These functions instead
+//! encode any `AsRef<[u8]>` container like `&[u8]` and `Bytes` do. Use them
+//! on a derived struct field with `#[rlp(bytes)]`, or wrap the bytes in
+//! `Bytes` where a field can't be annotated, such as `Vec<Bytes>`.
+
+use crate::{types::Header, DecodeError, Encodable};
+use alloc::vec::Vec;
+use bytes::{Buf, BufMut};
+
+pub fn encode<T: AsRef<[u8]> + ?Sized>(value: &T, out: &mut dyn BufMut) {
+    value.as_ref().encode(out)
+}
+
+pub fn length<T: AsRef<[u8]> + ?Sized>(value: &T) -> usize {
+    value.as_ref().length()
+}
+
+pub fn decode<T: From<Vec<u8>>>(buf: &mut &[u8]) -> Result<T, DecodeError> {
+    let h = Header::decode(buf)?;
+    if h.list {
+        return Err(DecodeError::UnexpectedList);
+    }
+    let v = buf[..h.payload_length].to_vec();
+    buf.advance(h.payload_length);
+
+    Ok(v.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+    use hex_literal::hex;
+
+    #[test]
+    fn byte_vec() {
+        let v = hex!("ABBA").to_vec();
+        let mut out = BytesMut::new();
+        encode(&v, &mut out);
+        assert_eq!(out[..], hex!("82abba")[..]);
+        assert_eq!(length(&v), 3);
+
+        let mut buf = &out[..];
+        assert_eq!(decode::<Vec<u8>>(&mut buf), Ok(v));
+        assert!(buf.is_empty());
+
+        assert_eq!(decode::<Vec<u8>>(&mut &hex!("7b")[..]), Ok(vec![0x7b]));
+        assert_eq!(
+            decode::<Vec<u8>>(&mut &hex!("c0")[..]),
+            Err(DecodeError::UnexpectedList)
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{
+    decode::decode_item, incremental::peek_item_length_checked, stream::invalid_data, Decodable,
+    Encodable, EncodeError, ItemLength,
+};
+use bytes::BytesMut;
+use core::marker::PhantomData;
+use std::io;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Frames a stream as back-to-back RLP items, each decoded as a `T` with
+/// [`Decodable::decode_bytes`], so `Bytes` fields share the frame's buffer.
+///
+/// Frames longer than `max_frame_size` are rejected, as soon as their header
+/// is received when decoding. Decoding errors are reported as [`io::Error`]s
+/// of kind [`io::ErrorKind::InvalidData`] wrapping the
+/// [`DecodeError`](crate::DecodeError), and values too long to send as ones of
+/// kind [`io::ErrorKind::InvalidInput`] wrapping an [`EncodeError`].
+#[derive(Debug)]
+pub struct RlpCodec<T> {
+    max_frame_size: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> RlpCodec<T> {
+    pub fn new(max_frame_size: usize) -> Self {
+        Self {
+            max_frame_size,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+}
+
+impl<T> Clone for RlpCodec<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.max_frame_size)
+    }
+}
+
+impl<T: Decodable> Decoder for RlpCodec<T> {
+    type Item = T;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, io::Error> {
+        match peek_item_length_checked(src, self.max_frame_size).map_err(invalid_data)? {
+            ItemLength::Complete(length) => {
+                let frame = src.split_to(length).freeze();
+                decode_item(frame, T::decode_bytes)
+                    .map(Some)
+                    .map_err(invalid_data)
+            }
+            ItemLength::Incomplete { needed } => {
+                src.reserve(needed);
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<T: Encodable> Encoder<T> for RlpCodec<T> {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), io::Error> {
+        let length = item.length();
+        if length > self.max_frame_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                EncodeError::BufferTooSmall {
+                    length,
+                    capacity: self.max_frame_size,
+                },
+            ));
+        }
+
+        dst.reserve(length);
+        item.encode_to(dst);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DecodeError;
+    use bytes::Bytes;
+    use futures::{SinkExt, StreamExt};
+    use hex_literal::hex;
+    use tokio::io::AsyncWriteExt;
+    use tokio_util::codec::{FramedRead, FramedWrite};
+
+    #[tokio::test]
+    async fn rlp_codec_duplex() {
+        let (client, server) = tokio::io::duplex(4);
+        let mut sink = FramedWrite::new(client, RlpCodec::<Vec<Bytes>>::new(64));
+        let mut stream = FramedRead::new(server, RlpCodec::<Vec<Bytes>>::new(64));
+
+        let messages = [
+            vec![Bytes::from_static(b"cat"), Bytes::from_static(b"dog")],
+            vec![],
+            vec![Bytes::from_static(&[0xAB; 40])],
+        ];
+        let send = async {
+            for message in messages.clone() {
+                sink.send(message).await.unwrap();
+            }
+            sink.close().await.unwrap();
+        };
+        let receive = async {
+            let mut received = Vec::new();
+            while let Some(message) = stream.next().await {
+                received.push(message.unwrap());
+            }
+            received
+        };
+        let ((), received) = tokio::join!(send, receive);
+        assert_eq!(received, messages);
+    }
+
+    #[tokio::test]
+    async fn rlp_codec_limits() {
+        let mut sink = FramedWrite::new(Vec::new(), RlpCodec::<Bytes>::new(4));
+        let err = sink.send(Bytes::from_static(b"horse")).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            err.get_ref().unwrap().downcast_ref::<EncodeError>(),
+            Some(&EncodeError::BufferTooSmall {
+                length: 6,
+                capacity: 4
+            })
+        );
+
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut stream = FramedRead::new(server, RlpCodec::<Bytes>::new(4));
+        client.write_all(&hex!("83646f67b90100")).await.unwrap();
+        assert_eq!(
+            stream.next().await.unwrap().unwrap(),
+            Bytes::from_static(b"dog")
+        );
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(
+            err.get_ref().unwrap().downcast_ref::<DecodeError>(),
+            Some(&DecodeError::ItemTooLarge {
+                length: 259,
+                max: 4
+            })
+        );
+
+        // Leftover bytes at the end of the stream are an error.
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut stream = FramedRead::new(server, RlpCodec::<Bytes>::new(4));
+        client.write_all(&hex!("83646f")).await.unwrap();
+        drop(client);
+        assert!(stream.next().await.unwrap().is_err());
+    }
+}
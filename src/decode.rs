@@ -1,8 +1,187 @@
 use crate::types::Header;
-use bytes::{Buf, Bytes, BytesMut};
+use arrayvec::ArrayVec;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use core::ops::Deref;
 
 pub trait Decodable: Sized {
     fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError>;
+
+    /// Like [`Self::decode`], but the error also tells where decoding failed.
+    /// This is slower, so prefer `decode` and call this to diagnose a failure.
+    fn decode_with_context(buf: &mut &[u8]) -> Result<Self, DetailedDecodeError> {
+        Ok(Self::decode(buf)?)
+    }
+
+    /// Decodes from a `Bytes` source, advancing it past the item. `Bytes`
+    /// fields are then views sharing the source's allocation instead of
+    /// copies. Types without byte fields keep decoding from a slice.
+    fn decode_bytes(buf: &mut Bytes) -> Result<Self, DecodeError> {
+        decode_bytes_with(buf, Self::decode)
+    }
+
+    /// Decodes from any [`Buf`], such as chained `Bytes` or a `VecDeque<u8>`,
+    /// whose data may be split into several chunks. An item held by the
+    /// current chunk is decoded in place, otherwise it is first copied into a
+    /// contiguous buffer.
+    fn decode_from_buf(buf: &mut impl Buf) -> Result<Self, DecodeError> {
+        let chunk = buf.chunk();
+        if let ItemLength::Complete(length) = Header::peek_item_length(chunk)? {
+            let v = decode_item(&chunk[..length], Self::decode)?;
+            buf.advance(length);
+            return Ok(v);
+        }
+
+        // The header may itself span chunks, so it is read a byte at a time.
+        let mut head = ArrayVec::<u8, 9>::new();
+        let (h, header_length) = loop {
+            if !buf.has_remaining() || head.is_full() {
+                return Err(DecodeError::InputTooShort);
+            }
+            head.push(buf.get_u8());
+
+            let mut payload = &head[..];
+            match Header::decode_prefix(&mut payload) {
+                Ok(h) => break (h, head.len() - payload.len()),
+                Err(DecodeError::InputTooShort) => continue,
+                Err(err) => return Err(err),
+            }
+        };
+        let length = header_length
+            .checked_add(h.payload_length)
+            .ok_or(DecodeError::Overflow)?;
+        let rest = length - head.len();
+        if buf.remaining() < rest {
+            return Err(DecodeError::InputTooShort);
+        }
+
+        let mut item = BytesMut::with_capacity(length);
+        item.extend_from_slice(&head);
+        item.put(buf.take(rest));
+        decode_item(&item[..], Self::decode)
+    }
+}
+
+/// Decodes `item`, a single framed item, with `decode`, which must use up the
+/// item.
+pub(crate) fn decode_item<B, T>(
+    mut item: B,
+    decode: impl FnOnce(&mut B) -> Result<T, DecodeError>,
+) -> Result<T, DecodeError>
+where
+    B: Deref<Target = [u8]>,
+{
+    let length = item.len();
+    let v = decode(&mut item)?;
+    if !item.is_empty() {
+        return Err(DecodeError::ListLengthMismatch {
+            expected: length,
+            got: length - item.len(),
+        });
+    }
+
+    Ok(v)
+}
+
+/// Decoding that may borrow from the input buffer, for example into `&'de [u8]`
+/// or `&'de str`, instead of copying.
+///
+/// The crate's [`Decodable`] types and derived ones are also
+/// `BorrowDecodable`, and so are `Option`s, `Vec`s, boxes and tuples of
+/// `BorrowDecodable` types, so that lists of borrowing structs can be decoded.
+pub trait BorrowDecodable<'de>: Sized {
+    fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, DecodeError>;
+}
+
+/// Implements [`BorrowDecodable`] with [`Decodable::decode`] for types that
+/// never borrow.
+macro_rules! borrow_decodable_owned {
+    ($($t:ty),+ $(,)?) => {
+        $(
+            impl<'de> BorrowDecodable<'de> for $t {
+                fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, DecodeError> {
+                    Decodable::decode(buf)
+                }
+            }
+        )+
+    };
+}
+
+/// Splits the payload of the list at the start of `buf` off it.
+fn borrow_list_payload<'de>(buf: &mut &'de [u8]) -> Result<&'de [u8], DecodeError> {
+    let h = Header::decode(buf)?;
+    if !h.list {
+        return Err(DecodeError::UnexpectedString);
+    }
+    let (payload, rest) = buf.split_at(h.payload_length);
+    *buf = rest;
+
+    Ok(payload)
+}
+
+impl<'de> BorrowDecodable<'de> for &'de [u8] {
+    fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, DecodeError> {
+        let h = Header::decode(buf)?;
+        if h.list {
+            return Err(DecodeError::UnexpectedList);
+        }
+        let (payload, rest) = buf.split_at(h.payload_length);
+        *buf = rest;
+
+        Ok(payload)
+    }
+}
+
+impl<'de, const N: usize> BorrowDecodable<'de> for &'de [u8; N] {
+    fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, DecodeError> {
+        let payload = <&[u8]>::borrow_decode(buf)?;
+        payload
+            .try_into()
+            .map_err(|_| DecodeError::UnexpectedLength)
+    }
+}
+
+impl<'de> BorrowDecodable<'de> for &'de str {
+    fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, DecodeError> {
+        let payload = <&[u8]>::borrow_decode(buf)?;
+        core::str::from_utf8(payload).map_err(|_| DecodeError::Custom("invalid string"))
+    }
+}
+
+/// Decoding of a struct's fields from a list payload, the counterpart of
+/// [`EncodableFields`](crate::EncodableFields). `buf` ends with the payload,
+/// so trailing fields are missing once it is empty.
+#[doc(hidden)]
+pub trait DecodableFields<'de>: Sized {
+    fn decode_fields(buf: &mut &'de [u8]) -> Result<Self, DecodeError>;
+
+    fn decode_fields_with_context(buf: &mut &'de [u8]) -> Result<Self, DetailedDecodeError> {
+        Ok(Self::decode_fields(buf)?)
+    }
+}
+
+/// Runs a slice decoder on `buf`, then advances `buf` past what it read.
+#[doc(hidden)]
+pub fn decode_bytes_with<T>(
+    buf: &mut Bytes,
+    decode: impl FnOnce(&mut &[u8]) -> Result<T, DecodeError>,
+) -> Result<T, DecodeError> {
+    let mut view = &buf[..];
+    let v = decode(&mut view)?;
+    buf.advance(buf.len() - view.len());
+    Ok(v)
+}
+
+/// Reads a list header from `buf` and splits the list's payload off it.
+#[doc(hidden)]
+pub fn split_list_payload(buf: &mut Bytes) -> Result<Bytes, DecodeError> {
+    let mut view = &buf[..];
+    let h = Header::decode(&mut view)?;
+    if !h.list {
+        return Err(DecodeError::UnexpectedString);
+    }
+    buf.advance(buf.len() - view.len());
+
+    Ok(buf.split_to(h.payload_length))
 }
 
 #[cfg(feature = "alloc")]
@@ -16,6 +195,14 @@ mod alloc_impl {
         fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
             T::decode(buf).map(::alloc::boxed::Box::new)
         }
+
+        fn decode_with_context(buf: &mut &[u8]) -> Result<Self, DetailedDecodeError> {
+            T::decode_with_context(buf).map(::alloc::boxed::Box::new)
+        }
+
+        fn decode_bytes(buf: &mut Bytes) -> Result<Self, DecodeError> {
+            T::decode_bytes(buf).map(::alloc::boxed::Box::new)
+        }
     }
 
     impl<T> Decodable for ::alloc::sync::Arc<T>
@@ -25,8 +212,30 @@ mod alloc_impl {
         fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
             T::decode(buf).map(::alloc::sync::Arc::new)
         }
+
+        fn decode_with_context(buf: &mut &[u8]) -> Result<Self, DetailedDecodeError> {
+            T::decode_with_context(buf).map(::alloc::sync::Arc::new)
+        }
+
+        fn decode_bytes(buf: &mut Bytes) -> Result<Self, DecodeError> {
+            T::decode_bytes(buf).map(::alloc::sync::Arc::new)
+        }
     }
 
+    impl<'de, T: BorrowDecodable<'de>> BorrowDecodable<'de> for ::alloc::boxed::Box<T> {
+        fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, DecodeError> {
+            T::borrow_decode(buf).map(::alloc::boxed::Box::new)
+        }
+    }
+
+    impl<'de, T: BorrowDecodable<'de>> BorrowDecodable<'de> for ::alloc::sync::Arc<T> {
+        fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, DecodeError> {
+            T::borrow_decode(buf).map(::alloc::sync::Arc::new)
+        }
+    }
+
+    borrow_decodable_owned!(::alloc::string::String);
+
     impl Decodable for ::alloc::string::String {
         fn decode(from: &mut &[u8]) -> Result<Self, DecodeError> {
             let h = Header::decode(from)?;
@@ -53,6 +262,8 @@ pub enum DecodeError {
     UnexpectedString,
     UnexpectedList,
     ListLengthMismatch { expected: usize, got: usize },
+    UnknownTag(u64),
+    ItemTooLarge { length: usize, max: usize },
     Custom(&'static str),
 }
 
@@ -73,13 +284,172 @@ impl core::fmt::Display for DecodeError {
             DecodeError::ListLengthMismatch { expected, got } => {
                 write!(f, "list length mismatch: expected {expected}, got {got}")
             }
+            DecodeError::UnknownTag(tag) => write!(f, "unknown tag {tag}"),
+            DecodeError::ItemTooLarge { length, max } => {
+                write!(f, "item too large: {length} bytes, max {max}")
+            }
             DecodeError::Custom(err) => write!(f, "{err}"),
         }
     }
 }
 
+/// A step of the path to a value that failed to decode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathSegment {
+    Field(&'static str),
+    Index(usize),
+}
+
+/// Maximum number of path segments kept by [`DetailedDecodeError`]. Deeper
+/// paths lose their innermost segments.
+#[cfg(feature = "alloc")]
+pub const MAX_ERROR_PATH_DEPTH: usize = 32;
+/// Maximum number of path segments kept by [`DetailedDecodeError`]. Deeper
+/// paths lose their innermost segments. Without `alloc` the path is stored
+/// inline, so only a few segments are kept.
+#[cfg(not(feature = "alloc"))]
+pub const MAX_ERROR_PATH_DEPTH: usize = 3;
+
+// Keeps the error small where it can allocate, as it is returned by value
+// through every level.
+#[cfg(feature = "alloc")]
+type ErrorPath = alloc::vec::Vec<PathSegment>;
+#[cfg(not(feature = "alloc"))]
+type ErrorPath = ArrayVec<PathSegment, MAX_ERROR_PATH_DEPTH>;
+
+/// [`DecodeError`] with the position of the item that failed to decode,
+/// returned by [`Decodable::decode_with_context`]. Derived types record their
+/// field names and lists the index of the element, so that the error reads
+/// like `Block.transactions[17].access_list[2].storage_keys`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DetailedDecodeError {
+    pub error: DecodeError,
+    /// Byte offset of the failed item from the start of the decoded input.
+    pub offset: usize,
+    /// Name of the outermost derived type, when the path starts with one.
+    pub root: Option<&'static str>,
+    // Innermost segment first.
+    path: ErrorPath,
+}
+
+impl DetailedDecodeError {
+    /// Path from the decoded value to the one that failed.
+    pub fn path(&self) -> impl Iterator<Item = &PathSegment> {
+        self.path.iter().rev()
+    }
+
+    /// Moves the error into an enclosing item, which starts `offset` bytes
+    /// before the failed one, through `segment` if any.
+    #[doc(hidden)]
+    pub fn nest(mut self, offset: usize, segment: Option<PathSegment>) -> Self {
+        self.offset += offset;
+        if let Some(segment) = segment {
+            if self.path.len() == MAX_ERROR_PATH_DEPTH {
+                self.path.remove(0);
+            }
+            self.path.push(segment);
+            self.root = None;
+        }
+        self
+    }
+
+    #[doc(hidden)]
+    pub fn in_type(mut self, name: &'static str) -> Self {
+        self.root = Some(name);
+        self
+    }
+}
+
+impl From<DecodeError> for DetailedDecodeError {
+    fn from(error: DecodeError) -> Self {
+        Self {
+            error,
+            offset: 0,
+            root: None,
+            path: ErrorPath::new(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DetailedDecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl core::fmt::Display for DetailedDecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} at byte {}", self.error, self.offset)?;
+        if self.root.is_none() && self.path.is_empty() {
+            return Ok(());
+        }
+
+        write!(f, " in ")?;
+        let mut first = true;
+        if let Some(root) = self.root {
+            write!(f, "{root}")?;
+            first = false;
+        }
+        for segment in self.path() {
+            match segment {
+                PathSegment::Field(name) if first => write!(f, "{name}")?,
+                PathSegment::Field(name) => write!(f, ".{name}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// How much of the item at the start of a buffer has been received, returned
+/// by [`Header::peek_item_length`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemLength {
+    /// The buffer starts with a complete item of this many bytes.
+    Complete(usize),
+    /// At least `needed` more bytes are required. The count is exact once the
+    /// header has been received, and `1` until then.
+    Incomplete { needed: usize },
+}
+
 impl Header {
     pub fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        let h = Self::decode_prefix(buf)?;
+
+        if buf.remaining() < h.payload_length {
+            return Err(DecodeError::InputTooShort);
+        }
+
+        Ok(h)
+    }
+
+    /// Length of the item at the start of `buf`, without requiring its
+    /// payload to have been received. Use this to frame items read from a
+    /// stream.
+    pub fn peek_item_length(buf: &[u8]) -> Result<ItemLength, DecodeError> {
+        let mut payload = buf;
+        let h = match Self::decode_prefix(&mut payload) {
+            Err(DecodeError::InputTooShort) => return Ok(ItemLength::Incomplete { needed: 1 }),
+            res => res?,
+        };
+        let header_length = buf.len() - payload.len();
+        let length = header_length
+            .checked_add(h.payload_length)
+            .ok_or(DecodeError::Overflow)?;
+
+        Ok(if buf.len() < length {
+            ItemLength::Incomplete {
+                needed: length - buf.len(),
+            }
+        } else {
+            ItemLength::Complete(length)
+        })
+    }
+
+    /// Decodes the header without checking that the payload follows.
+    fn decode_prefix(buf: &mut &[u8]) -> Result<Self, DecodeError> {
         if !buf.has_remaining() {
             return Err(DecodeError::InputTooShort);
         }
@@ -156,10 +526,6 @@ impl Header {
             }
         };
 
-        if buf.remaining() < h.payload_length {
-            return Err(DecodeError::InputTooShort);
-        }
-
         Ok(h)
     }
 }
@@ -214,6 +580,8 @@ decode_integer!(u32);
 decode_integer!(u64);
 decode_integer!(u128);
 
+borrow_decodable_owned!(usize, u8, u16, u32, u64, u128, bool, BytesMut, Bytes);
+
 impl Decodable for bool {
     fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
         Ok(match u8::decode(buf)? {
@@ -226,6 +594,8 @@ impl Decodable for bool {
 
 #[cfg(feature = "ethnum")]
 decode_integer!(ethnum::U256);
+#[cfg(feature = "ethnum")]
+borrow_decodable_owned!(ethnum::U256);
 
 #[cfg(feature = "ethereum-types")]
 mod ethereum_types_support {
@@ -250,6 +620,8 @@ mod ethereum_types_support {
     fixed_hash_impl!(H520);
     fixed_hash_impl!(Bloom);
 
+    borrow_decodable_owned!(H64, H128, H160, H256, H512, H520, Bloom, U64, U128, U256, U512);
+
     macro_rules! int_impl {
         ($t:ty) => {
             impl Decodable for $t {
@@ -266,14 +638,14 @@ mod ethereum_types_support {
                         return Err(DecodeError::InputTooShort);
                     }
                     let v = Self::from_big_endian(
-                        &static_left_pad::<LEN>(&buf[..h.payload_length]).ok_or(DecodeError::LeadingZero)?,
+                        &static_left_pad::<LEN>(&buf[..h.payload_length])
+                            .ok_or(DecodeError::LeadingZero)?,
                     );
                     buf.advance(h.payload_length);
                     Ok(v)
                 }
             }
-
-        }
+        };
     }
 
     int_impl!(U64);
@@ -300,6 +672,12 @@ impl<const N: usize> Decodable for [u8; N] {
     }
 }
 
+impl<'de, const N: usize> BorrowDecodable<'de> for [u8; N] {
+    fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, DecodeError> {
+        Self::decode(buf)
+    }
+}
+
 impl Decodable for BytesMut {
     fn decode(from: &mut &[u8]) -> Result<Self, DecodeError> {
         let h = Header::decode(from)?;
@@ -318,10 +696,205 @@ impl Decodable for Bytes {
     fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
         BytesMut::decode(buf).map(BytesMut::freeze)
     }
+
+    fn decode_bytes(buf: &mut Bytes) -> Result<Self, DecodeError> {
+        let mut view = &buf[..];
+        let h = Header::decode(&mut view)?;
+        if h.list {
+            return Err(DecodeError::UnexpectedList);
+        }
+        buf.advance(buf.len() - view.len());
+
+        Ok(buf.split_to(h.payload_length))
+    }
 }
 
+/// See the `Encodable` impl for the `Option` convention.
+impl<T: Decodable> Decodable for Option<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        let h = Header::decode(buf)?;
+        if !h.list {
+            return Err(DecodeError::UnexpectedString);
+        }
+        if h.payload_length == 0 {
+            return Ok(None);
+        }
+
+        let payload_view = &mut &buf[..h.payload_length];
+        let v = T::decode(payload_view)?;
+        if !payload_view.is_empty() {
+            return Err(DecodeError::ListLengthMismatch {
+                expected: h.payload_length,
+                got: h.payload_length - payload_view.len(),
+            });
+        }
+        buf.advance(h.payload_length);
+
+        Ok(Some(v))
+    }
+
+    fn decode_with_context(buf: &mut &[u8]) -> Result<Self, DetailedDecodeError> {
+        let len = buf.len();
+        let h = Header::decode(buf)?;
+        if !h.list {
+            return Err(DecodeError::UnexpectedString.into());
+        }
+        if h.payload_length == 0 {
+            return Ok(None);
+        }
+
+        let header_length = len - buf.len();
+        let payload_view = &mut &buf[..h.payload_length];
+        let v = T::decode_with_context(payload_view).map_err(|e| e.nest(header_length, None))?;
+        if !payload_view.is_empty() {
+            return Err(DecodeError::ListLengthMismatch {
+                expected: h.payload_length,
+                got: h.payload_length - payload_view.len(),
+            }
+            .into());
+        }
+        buf.advance(h.payload_length);
+
+        Ok(Some(v))
+    }
+    fn decode_bytes(buf: &mut Bytes) -> Result<Self, DecodeError> {
+        let mut payload = split_list_payload(buf)?;
+        if payload.is_empty() {
+            return Ok(None);
+        }
+
+        let payload_length = payload.len();
+        let v = T::decode_bytes(&mut payload)?;
+        if !payload.is_empty() {
+            return Err(DecodeError::ListLengthMismatch {
+                expected: payload_length,
+                got: payload_length - payload.len(),
+            });
+        }
+
+        Ok(Some(v))
+    }
+}
+
+impl<'de, T: BorrowDecodable<'de>> BorrowDecodable<'de> for Option<T> {
+    fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, DecodeError> {
+        let payload = &mut borrow_list_payload(buf)?;
+        if payload.is_empty() {
+            return Ok(None);
+        }
+
+        let payload_length = payload.len();
+        let v = T::borrow_decode(payload)?;
+        if !payload.is_empty() {
+            return Err(DecodeError::ListLengthMismatch {
+                expected: payload_length,
+                got: payload_length - payload.len(),
+            });
+        }
+
+        Ok(Some(v))
+    }
+}
+
+macro_rules! tuple_impl {
+    ($($name:ident)+) => {
+        impl<$($name: Decodable),+> Decodable for ($($name,)+) {
+            fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+                let h = Header::decode(buf)?;
+                if !h.list {
+                    return Err(DecodeError::UnexpectedString);
+                }
+
+                let payload_view = &mut &buf[..h.payload_length];
+                let v = ($($name::decode(payload_view)?,)+);
+                if !payload_view.is_empty() {
+                    return Err(DecodeError::ListLengthMismatch {
+                        expected: h.payload_length,
+                        got: h.payload_length - payload_view.len(),
+                    });
+                }
+                buf.advance(h.payload_length);
+
+                Ok(v)
+            }
+
+            fn decode_with_context(buf: &mut &[u8]) -> Result<Self, DetailedDecodeError> {
+                let len = buf.len();
+                let h = Header::decode(buf)?;
+                if !h.list {
+                    return Err(DecodeError::UnexpectedString.into());
+                }
+
+                let header_length = len - buf.len();
+                let payload_view = &mut &buf[..h.payload_length];
+                let mut indices = 0..;
+                let v = ($({
+                    let offset = header_length + h.payload_length - payload_view.len();
+                    let segment = PathSegment::Index(indices.next().unwrap_or_default());
+                    $name::decode_with_context(payload_view)
+                        .map_err(|e| e.nest(offset, Some(segment)))?
+                },)+);
+                if !payload_view.is_empty() {
+                    return Err(DecodeError::ListLengthMismatch {
+                        expected: h.payload_length,
+                        got: h.payload_length - payload_view.len(),
+                    }
+                    .into());
+                }
+                buf.advance(h.payload_length);
+
+                Ok(v)
+            }
+
+            fn decode_bytes(buf: &mut Bytes) -> Result<Self, DecodeError> {
+                let mut payload = split_list_payload(buf)?;
+                let payload_length = payload.len();
+                let v = ($($name::decode_bytes(&mut payload)?,)+);
+                if !payload.is_empty() {
+                    return Err(DecodeError::ListLengthMismatch {
+                        expected: payload_length,
+                        got: payload_length - payload.len(),
+                    });
+                }
+
+                Ok(v)
+            }
+        }
+
+        impl<'de, $($name: BorrowDecodable<'de>),+> BorrowDecodable<'de> for ($($name,)+) {
+            fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, DecodeError> {
+                let payload = &mut borrow_list_payload(buf)?;
+                let payload_length = payload.len();
+                let v = ($($name::borrow_decode(payload)?,)+);
+                if !payload.is_empty() {
+                    return Err(DecodeError::ListLengthMismatch {
+                        expected: payload_length,
+                        got: payload_length - payload.len(),
+                    });
+                }
+
+                Ok(v)
+            }
+        }
+    };
+}
+
+tuple_impl!(A);
+tuple_impl!(A B);
+tuple_impl!(A B C);
+tuple_impl!(A B C D);
+tuple_impl!(A B C D E);
+tuple_impl!(A B C D E F);
+tuple_impl!(A B C D E F G);
+tuple_impl!(A B C D E F G H);
+tuple_impl!(A B C D E F G H I);
+tuple_impl!(A B C D E F G H I J);
+tuple_impl!(A B C D E F G H I J K);
+tuple_impl!(A B C D E F G H I J K L);
+
 pub struct Rlp<'a> {
     payload_view: &'a [u8],
+    payload_length: usize,
 }
 
 impl<'a> Rlp<'a> {
@@ -332,7 +905,10 @@ impl<'a> Rlp<'a> {
         }
 
         let payload_view = &payload[..h.payload_length];
-        Ok(Self { payload_view })
+        Ok(Self {
+            payload_view,
+            payload_length: h.payload_length,
+        })
     }
 
     pub fn get_next<T: Decodable>(&mut self) -> Result<Option<T>, DecodeError> {
@@ -342,6 +918,29 @@ impl<'a> Rlp<'a> {
 
         Ok(Some(T::decode(&mut self.payload_view)?))
     }
+
+    /// Skips the items that were not read yet and returns their number. Use
+    /// this to ignore additional list elements, as devp2p (EIP-8) requires.
+    pub fn skip_rest(&mut self) -> Result<usize, DecodeError> {
+        let mut skipped = 0;
+        while !self.payload_view.is_empty() {
+            let h = Header::decode(&mut self.payload_view)?;
+            self.payload_view.advance(h.payload_length);
+            skipped += 1;
+        }
+        Ok(skipped)
+    }
+
+    /// Checks that every item of the list has been read.
+    pub fn finish(self) -> Result<(), DecodeError> {
+        if !self.payload_view.is_empty() {
+            return Err(DecodeError::ListLengthMismatch {
+                expected: self.payload_length,
+                got: self.payload_length - self.payload_view.len(),
+            });
+        }
+        Ok(())
+    }
 }
 
 #[cfg(feature = "alloc")]
@@ -366,10 +965,61 @@ where
 
         Ok(to)
     }
+
+    fn decode_with_context(buf: &mut &[u8]) -> Result<Self, DetailedDecodeError> {
+        let len = buf.len();
+        let h = Header::decode(buf)?;
+        if !h.list {
+            return Err(DecodeError::UnexpectedString.into());
+        }
+
+        let header_length = len - buf.len();
+        let payload_view = &mut &buf[..h.payload_length];
+
+        let mut to = alloc::vec::Vec::new();
+        while !payload_view.is_empty() {
+            let offset = header_length + h.payload_length - payload_view.len();
+            let segment = PathSegment::Index(to.len());
+            to.push(
+                E::decode_with_context(payload_view).map_err(|e| e.nest(offset, Some(segment)))?,
+            );
+        }
+
+        buf.advance(h.payload_length);
+
+        Ok(to)
+    }
+    fn decode_bytes(buf: &mut Bytes) -> Result<Self, DecodeError> {
+        let mut payload = split_list_payload(buf)?;
+
+        let mut to = alloc::vec::Vec::new();
+        while !payload.is_empty() {
+            to.push(E::decode_bytes(&mut payload)?);
+        }
+
+        Ok(to)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de, E> BorrowDecodable<'de> for alloc::vec::Vec<E>
+where
+    E: BorrowDecodable<'de>,
+{
+    fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, DecodeError> {
+        let payload = &mut borrow_list_payload(buf)?;
+
+        let mut to = alloc::vec::Vec::new();
+        while !payload.is_empty() {
+            to.push(E::borrow_decode(payload)?);
+        }
+
+        Ok(to)
+    }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     extern crate alloc;
 
     use super::*;
@@ -378,6 +1028,17 @@ mod tests {
     use ethnum::AsU256;
     use hex_literal::hex;
 
+    /// Decodes only the first byte of its item.
+    #[derive(Debug, PartialEq)]
+    pub(crate) struct FirstByte;
+
+    impl Decodable for FirstByte {
+        fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+            *buf = buf.get(1..).ok_or(DecodeError::InputTooShort)?;
+            Ok(Self)
+        }
+    }
+
     fn check_decode<T, IT>(fixtures: IT)
     where
         T: Decodable + PartialEq + Debug,
@@ -407,9 +1068,9 @@ mod tests {
     #[test]
     fn rlp_strings() {
         check_decode::<Bytes, _>(vec![
-            (Ok((&hex!("00")[..]).to_vec().into()), &hex!("00")[..]),
+            (Ok(hex!("00")[..].to_vec().into()), &hex!("00")[..]),
             (
-                Ok((&hex!("6f62636465666768696a6b6c6d")[..]).to_vec().into()),
+                Ok(hex!("6f62636465666768696a6b6c6d")[..].to_vec().into()),
                 &hex!("8D6F62636465666768696A6B6C6D")[..],
             ),
             (Err(DecodeError::UnexpectedList), &hex!("C0")[..]),
@@ -498,4 +1159,245 @@ mod tests {
             ),
         ])
     }
+
+    #[test]
+    fn rlp_option() {
+        check_decode::<Option<u64>, _>(vec![
+            (Ok(None), &hex!("c0")[..]),
+            (Ok(Some(0)), &hex!("c180")[..]),
+            (Ok(Some(0xFFCCB5)), &hex!("c483ffccb5")[..]),
+            (Err(DecodeError::UnexpectedString), &hex!("80")[..]),
+            (
+                Err(DecodeError::ListLengthMismatch {
+                    expected: 2,
+                    got: 1,
+                }),
+                &hex!("c20101")[..],
+            ),
+        ]);
+    }
+
+    #[test]
+    fn rlp_tuples() {
+        check_decode::<(u64, bool, [u8; 2]), _>(vec![
+            (
+                Ok((0xFFCCB5, true, hex!("ABBA"))),
+                &hex!("c883ffccb50182abba")[..],
+            ),
+            (Err(DecodeError::UnexpectedString), &hex!("83ffccb5")[..]),
+            (Err(DecodeError::InputTooShort), &hex!("c483ffccb5")[..]),
+            (
+                Err(DecodeError::ListLengthMismatch {
+                    expected: 9,
+                    got: 8,
+                }),
+                &hex!("c983ffccb50182abba01")[..],
+            ),
+        ]);
+
+        let err =
+            <(u64, (bool, u8))>::decode_with_context(&mut &hex!("c501c3018100")[..]).unwrap_err();
+        assert_eq!(err.error, DecodeError::NonCanonicalSingleByte);
+        assert_eq!(err.offset, 4);
+        assert!(err.to_string().ends_with(" at byte 4 in [1][1]"));
+    }
+
+    #[test]
+    fn detailed_error() {
+        let err = DetailedDecodeError::from(DecodeError::Overflow);
+        assert_eq!(err.to_string(), "overflow at byte 0");
+
+        let err = err
+            .nest(2, Some(PathSegment::Field("nonce")))
+            .in_type("Tx")
+            .nest(3, Some(PathSegment::Index(4)))
+            .nest(1, None);
+        assert_eq!(err.offset, 6);
+        assert_eq!(err.root, None);
+        assert_eq!(err.to_string(), "overflow at byte 6 in [4].nonce");
+
+        let err = (0..MAX_ERROR_PATH_DEPTH + 2)
+            .fold(err, |err, i| err.nest(0, Some(PathSegment::Index(i))));
+        assert_eq!(err.path().count(), MAX_ERROR_PATH_DEPTH);
+        assert_eq!(
+            err.path().next(),
+            Some(&PathSegment::Index(MAX_ERROR_PATH_DEPTH + 1))
+        );
+    }
+
+    #[test]
+    fn rlp_decode_from_buf() {
+        use alloc::collections::VecDeque;
+
+        let data = hex!("c883ffccb583ffc0b5");
+        let expected = vec![0xFFCCB5_u64, 0xFFC0B5];
+
+        // Split at every position, including inside the header.
+        for i in 0..=data.len() {
+            let mut buf =
+                Bytes::copy_from_slice(&data[..i]).chain(Bytes::copy_from_slice(&data[i..]));
+            assert_eq!(
+                alloc::vec::Vec::<u64>::decode_from_buf(&mut buf),
+                Ok(expected.clone())
+            );
+            assert!(!buf.has_remaining());
+        }
+
+        let mut long = vec![0xb9, 0x01, 0x00];
+        long.extend_from_slice(&[0xab; 256]);
+        for i in [1, 2, 3, 100] {
+            let mut buf =
+                Bytes::copy_from_slice(&long[..i]).chain(Bytes::copy_from_slice(&long[i..]));
+            assert_eq!(
+                BytesMut::decode_from_buf(&mut buf).unwrap()[..],
+                [0xab; 256]
+            );
+        }
+
+        // The queue's storage wraps around, splitting the second item.
+        let mut queue = VecDeque::with_capacity(4);
+        let skipped = queue.capacity() - 1;
+        queue.extend(core::iter::repeat_n(0, skipped));
+        queue.push_back(0xc3);
+        queue.drain(..skipped);
+        queue.extend(hex!("010203"));
+        assert_eq!(queue.as_slices().0, &hex!("c3"));
+        assert_eq!(
+            alloc::vec::Vec::<u8>::decode_from_buf(&mut queue),
+            Ok(vec![1, 2, 3])
+        );
+
+        let mut buf =
+            Bytes::copy_from_slice(&hex!("c3")).chain(Bytes::copy_from_slice(&hex!("0102")));
+        assert_eq!(
+            alloc::vec::Vec::<u8>::decode_from_buf(&mut buf),
+            Err(DecodeError::InputTooShort)
+        );
+        let mut buf =
+            Bytes::copy_from_slice(&hex!("81")).chain(Bytes::copy_from_slice(&hex!("00")));
+        assert_eq!(
+            u8::decode_from_buf(&mut buf),
+            Err(DecodeError::NonCanonicalSingleByte)
+        );
+
+        // Values must be the whole item.
+        let mismatch = Err(DecodeError::ListLengthMismatch {
+            expected: 3,
+            got: 1,
+        });
+        assert_eq!(
+            FirstByte::decode_from_buf(&mut &hex!("820400")[..]),
+            mismatch
+        );
+        let mut buf =
+            Bytes::copy_from_slice(&hex!("82")).chain(Bytes::copy_from_slice(&hex!("0400")));
+        assert_eq!(FirstByte::decode_from_buf(&mut buf), mismatch);
+    }
+
+    #[test]
+    fn rlp_decode_bytes() {
+        let data = Bytes::from_static(&hex!("cac88363617483646f67c08180"));
+        let start = data.as_ptr() as usize;
+
+        let mut buf = data.clone();
+        let (animals, empty) =
+            <(alloc::vec::Vec<Bytes>, Option<Bytes>)>::decode_bytes(&mut buf).unwrap();
+        assert_eq!(animals, [&b"cat"[..], &b"dog"[..]]);
+        assert_eq!(animals[0].as_ptr() as usize, start + 3);
+        assert_eq!(animals[1].as_ptr() as usize, start + 7);
+        assert_eq!(empty, None);
+        assert_eq!(buf[..], hex!("8180"));
+        assert_eq!(u8::decode_bytes(&mut buf), Ok(0x80));
+        assert!(buf.is_empty());
+
+        assert_eq!(
+            Bytes::decode_bytes(&mut data.clone()),
+            Err(DecodeError::UnexpectedList)
+        );
+        assert_eq!(
+            alloc::vec::Vec::<Bytes>::decode_bytes(&mut data.slice(2..)),
+            Err(DecodeError::UnexpectedString)
+        );
+        assert_eq!(
+            <(Bytes,)>::decode_bytes(&mut data.slice(..8)),
+            Err(DecodeError::InputTooShort)
+        );
+        assert_eq!(
+            <(alloc::vec::Vec<Bytes>,)>::decode_bytes(&mut data.clone()),
+            Err(DecodeError::ListLengthMismatch {
+                expected: 10,
+                got: 9
+            })
+        );
+    }
+
+    #[test]
+    fn rlp_borrowed() {
+        let data = hex!("8D6F62636465666768696A6B6C6D");
+        let mut buf = &data[..];
+        let s = <&[u8]>::borrow_decode(&mut buf).unwrap();
+        assert_eq!(s, &data[1..]);
+        assert!(core::ptr::eq(s, &data[1..]));
+        assert!(buf.is_empty());
+
+        assert_eq!(<&str>::borrow_decode(&mut &data[..]), Ok("obcdefghijklm"));
+        assert_eq!(
+            <&[u8; 13]>::borrow_decode(&mut &data[..]),
+            Ok(&hex!("6f62636465666768696a6b6c6d"))
+        );
+        assert_eq!(
+            <&[u8; 12]>::borrow_decode(&mut &data[..]),
+            Err(DecodeError::UnexpectedLength)
+        );
+        assert_eq!(
+            <&str>::borrow_decode(&mut &hex!("81FF")[..]),
+            Err(DecodeError::Custom("invalid string"))
+        );
+        assert_eq!(
+            <&[u8]>::borrow_decode(&mut &hex!("C0")[..]),
+            Err(DecodeError::UnexpectedList)
+        );
+        assert_eq!(u64::borrow_decode(&mut &hex!("820505")[..]), Ok(0x0505));
+
+        let data = hex!("C6C20161C20262");
+        assert_eq!(
+            Vec::<(u8, &str)>::borrow_decode(&mut &data[..]),
+            Ok(vec![(1, "a"), (2, "b")])
+        );
+        assert_eq!(
+            <Option<&[u8]>>::borrow_decode(&mut &hex!("C161")[..]),
+            Ok(Some(&b"a"[..]))
+        );
+        assert_eq!(
+            <Option<&[u8]>>::borrow_decode(&mut &hex!("C0")[..]),
+            Ok(None)
+        );
+        assert_eq!(
+            <(&str,)>::borrow_decode(&mut &hex!("C26162")[..]),
+            Err(DecodeError::ListLengthMismatch {
+                expected: 2,
+                got: 1
+            })
+        );
+    }
+
+    #[test]
+    fn rlp_view_trailing_items() {
+        let data = hex!("C90583BBCCB5C2010203");
+
+        let mut view = Rlp::new(&data).unwrap();
+        assert_eq!(view.get_next::<u64>().unwrap(), Some(5));
+        assert_eq!(
+            Rlp::new(&data).unwrap().finish(),
+            Err(DecodeError::ListLengthMismatch {
+                expected: 9,
+                got: 0
+            })
+        );
+        assert_eq!(view.skip_rest().unwrap(), 3);
+        assert_eq!(view.finish(), Ok(()));
+
+        let mut view = Rlp::new(&hex!("C3B90100")[..]).unwrap();
+        assert_eq!(view.skip_rest(), Err(DecodeError::InputTooShort));
+    }
 }
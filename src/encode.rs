@@ -1,8 +1,8 @@
-use crate::types::*;
+use crate::{length_cache::LengthCache, types::*};
 use arrayvec::ArrayVec;
 use auto_impl::auto_impl;
-use bytes::{BufMut, Bytes, BytesMut};
-use core::borrow::Borrow;
+use bytes::{buf::UninitSlice, BufMut, Bytes, BytesMut};
+use core::{borrow::Borrow, fmt};
 
 fn zeroless_view(v: &impl AsRef<[u8]>) -> &[u8] {
     let v = v.as_ref();
@@ -10,7 +10,7 @@ fn zeroless_view(v: &impl AsRef<[u8]>) -> &[u8] {
 }
 
 impl Header {
-    pub fn encode(&self, out: &mut dyn BufMut) {
+    pub fn encode<B: BufMut + ?Sized>(&self, out: &mut B) {
         if self.payload_length < 56 {
             let code = if self.list {
                 EMPTY_LIST_CODE
@@ -49,10 +49,37 @@ pub unsafe trait MaxEncodedLenAssoc: Encodable {
     const LEN: usize;
 }
 
+/// Encoding of a struct's fields without the list header, so that they can be
+/// inlined into another struct's list with `#[rlp(flatten)]`.
+#[doc(hidden)]
+pub trait EncodableFields {
+    fn fields_length(&self) -> usize;
+    fn encode_fields(&self, out: &mut dyn BufMut);
+    fn encode_fields_to<B: BufMut + ?Sized>(&self, out: &mut B)
+    where
+        Self: Sized,
+    {
+        self.encode_fields(&mut &mut *out)
+    }
+    fn cache_fields_lengths(&self, _cache: &mut LengthCache) -> usize {
+        self.fields_length()
+    }
+    fn encode_fields_from_cache(&self, _cache: &mut LengthCache, out: &mut dyn BufMut) {
+        self.encode_fields(out)
+    }
+}
+
+/// Maximum length of [`EncodableFields::encode_fields`] output.
+#[doc(hidden)]
+pub unsafe trait MaxEncodedFieldsLen {
+    const FIELDS_LEN: usize;
+}
+
 /// Use this to define length of an encoded entity
 ///
 /// # Safety
-/// Invalid value can cause the encoder to crash.
+/// Invalid value can cause the encoder to crash. [`try_encode_fixed_size`]
+/// encodes on the stack without a declared length.
 #[macro_export]
 macro_rules! impl_max_encoded_len {
     ($t:ty, $len:block) => {
@@ -63,18 +90,99 @@ macro_rules! impl_max_encoded_len {
     };
 }
 
-#[auto_impl(&)]
-#[cfg_attr(feature = "alloc", auto_impl(Box, Arc))]
+/// Implemented for references, `Box`es and `Arc`s of any encodable type,
+/// trait objects included. Their [`Self::encode_to`] goes through `encode`, as
+/// the pointee may be unsized: encode the pointee itself for static dispatch.
+#[cfg_attr(not(feature = "alloc"), auto_impl(&))]
+#[cfg_attr(feature = "alloc", auto_impl(&, Box, Arc))]
 pub trait Encodable {
     fn encode(&self, out: &mut dyn BufMut);
+    /// [`Self::encode`] with static dispatch of the writes to `out`, which
+    /// lets them be inlined. Implementations override it with their encoding
+    /// and forward `encode` to it; the default goes through `encode`.
+    #[auto_impl(keep_default_for(&, Box, Arc))]
+    fn encode_to<B: BufMut + ?Sized>(&self, out: &mut B)
+    where
+        Self: Sized,
+    {
+        // `&mut B` is sized even if `B` is not, so it can be a trait object.
+        self.encode(&mut &mut *out)
+    }
+    /// Length of the encoding. The default measures [`Self::encode`] output
+    /// with a [`LengthCounter`], so overriding it is only an optimization.
     fn length(&self) -> usize {
-        let mut out = BytesMut::new();
+        let mut out = LengthCounter::new();
         self.encode(&mut out);
         out.len()
     }
+    /// Records the payload lengths of the lists that
+    /// [`Self::encode_from_cache`] writes in `cache`, and returns the encoded
+    /// length. Only lists override it, see [`LengthCache`].
+    fn cache_lengths(&self, _cache: &mut LengthCache) -> usize {
+        self.length()
+    }
+    /// [`Self::encode`] with the list headers cached by
+    /// [`Self::cache_lengths`].
+    fn encode_from_cache(&self, _cache: &mut LengthCache, out: &mut dyn BufMut) {
+        self.encode(out)
+    }
+}
+
+/// [`BufMut`] that discards the bytes put into it and only counts them, to
+/// measure an encoding without allocating.
+#[derive(Clone, Default)]
+pub struct LengthCounter {
+    len: usize,
+    // Handed out by `chunk_mut` for writers that fill the buffer in place.
+    scratch: [u8; 32],
 }
 
-impl<'a> Encodable for &'a [u8] {
+impl LengthCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of bytes put so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl fmt::Debug for LengthCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LengthCounter")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+unsafe impl BufMut for LengthCounter {
+    fn remaining_mut(&self) -> usize {
+        usize::MAX - self.len
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        self.len += cnt;
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        UninitSlice::new(&mut self.scratch)
+    }
+
+    fn put_slice(&mut self, src: &[u8]) {
+        self.len += src.len();
+    }
+
+    fn put_u8(&mut self, _: u8) {
+        self.len += 1;
+    }
+}
+
+impl Encodable for &[u8] {
     fn length(&self) -> usize {
         let mut len = self.len();
         if self.len() != 1 || self[0] >= EMPTY_STRING_CODE {
@@ -84,6 +192,10 @@ impl<'a> Encodable for &'a [u8] {
     }
 
     fn encode(&self, out: &mut dyn BufMut) {
+        self.encode_to(out)
+    }
+
+    fn encode_to<B: BufMut + ?Sized>(&self, out: &mut B) {
         if self.len() != 1 || self[0] >= EMPTY_STRING_CODE {
             Header {
                 list: false,
@@ -101,7 +213,11 @@ impl<const LEN: usize> Encodable for [u8; LEN] {
     }
 
     fn encode(&self, out: &mut dyn BufMut) {
-        (self as &[u8]).encode(out)
+        self.encode_to(out)
+    }
+
+    fn encode_to<B: BufMut + ?Sized>(&self, out: &mut B) {
+        (self as &[u8]).encode_to(out)
     }
 }
 
@@ -122,6 +238,10 @@ macro_rules! encodable_uint {
             }
 
             fn encode(&self, out: &mut dyn BufMut) {
+                self.encode_to(out)
+            }
+
+            fn encode_to<B: BufMut + ?Sized>(&self, out: &mut B) {
                 if *self == 0 {
                     out.put_u8(EMPTY_STRING_CODE);
                 } else if *self < <$t>::from(EMPTY_STRING_CODE) {
@@ -169,7 +289,11 @@ impl Encodable for bool {
     }
 
     fn encode(&self, out: &mut dyn BufMut) {
-        (*self as u8).encode(out)
+        self.encode_to(out)
+    }
+
+    fn encode_to<B: BufMut + ?Sized>(&self, out: &mut B) {
+        (*self as u8).encode_to(out)
     }
 }
 
@@ -196,7 +320,11 @@ mod ethereum_types_support {
                 }
 
                 fn encode(&self, out: &mut dyn bytes::BufMut) {
-                    self.0.encode(out)
+                    self.encode_to(out)
+                }
+
+                fn encode_to<B: bytes::BufMut + ?Sized>(&self, out: &mut B) {
+                    self.0.encode_to(out)
                 }
             }
             impl_max_encoded_len!($t, {
@@ -226,6 +354,10 @@ mod ethereum_types_support {
                 }
 
                 fn encode(&self, out: &mut dyn bytes::BufMut) {
+                    self.encode_to(out)
+                }
+
+                fn encode_to<B: bytes::BufMut + ?Sized>(&self, out: &mut B) {
                     if self.is_zero() {
                         out.put_u8(EMPTY_STRING_CODE);
                     } else if *self < <$t>::from(EMPTY_STRING_CODE) {
@@ -240,7 +372,7 @@ mod ethereum_types_support {
                     }
                 }
             }
-        }
+        };
     }
 
     int_impl!(U64);
@@ -261,7 +393,11 @@ macro_rules! slice_impl {
             }
 
             fn encode(&self, out: &mut dyn bytes::BufMut) {
-                (&self[..]).encode(out)
+                self.encode_to(out)
+            }
+
+            fn encode_to<B: bytes::BufMut + ?Sized>(&self, out: &mut B) {
+                (&self[..]).encode_to(out)
             }
         }
     };
@@ -270,6 +406,7 @@ macro_rules! slice_impl {
 #[cfg(feature = "alloc")]
 mod alloc_support {
     use super::*;
+    use crate::length_cache::list_cache_lengths;
 
     extern crate alloc;
 
@@ -282,13 +419,36 @@ mod alloc_support {
         }
 
         fn encode(&self, out: &mut dyn BufMut) {
-            encode_list(self, out)
+            self.encode_to(out)
+        }
+
+        fn encode_to<B: BufMut + ?Sized>(&self, out: &mut B) {
+            rlp_list_header::<T, T>(self).encode(out);
+            for x in self {
+                x.encode_to(out);
+            }
+        }
+
+        fn cache_lengths(&self, cache: &mut LengthCache) -> usize {
+            let slot = cache.begin_list();
+            let payload_length = list_cache_lengths::<T, T>(self, cache);
+            cache.end_list(slot, payload_length)
+        }
+
+        fn encode_from_cache(&self, cache: &mut LengthCache, out: &mut dyn BufMut) {
+            cache.list_header().encode(out);
+            for x in self {
+                x.encode_from_cache(cache, out);
+            }
         }
     }
 
     impl Encodable for ::alloc::string::String {
         fn encode(&self, out: &mut dyn BufMut) {
-            self.as_bytes().encode(out);
+            self.encode_to(out)
+        }
+        fn encode_to<B: BufMut + ?Sized>(&self, out: &mut B) {
+            self.as_bytes().encode_to(out)
         }
         fn length(&self) -> usize {
             self.as_bytes().length()
@@ -298,6 +458,110 @@ mod alloc_support {
 slice_impl!(Bytes);
 slice_impl!(BytesMut);
 
+/// `None` is encoded as the empty list and `Some(v)` as a list holding `v`,
+/// the same convention as parity's `rlp`. Unlike encoding `None` as the empty
+/// string, this cannot be mistaken for a `Some` value. `#[rlp(trailing)]`
+/// fields of derived structs are instead left out of the list when `None`.
+impl<T: Encodable> Encodable for Option<T> {
+    fn length(&self) -> usize {
+        let payload_length = self.as_ref().map_or(0, T::length);
+        length_of_length(payload_length) + payload_length
+    }
+
+    fn encode(&self, out: &mut dyn BufMut) {
+        self.encode_to(out)
+    }
+
+    fn encode_to<B: BufMut + ?Sized>(&self, out: &mut B) {
+        Header {
+            list: true,
+            payload_length: self.as_ref().map_or(0, T::length),
+        }
+        .encode(out);
+        if let Some(v) = self {
+            v.encode_to(out);
+        }
+    }
+
+    fn cache_lengths(&self, cache: &mut LengthCache) -> usize {
+        let slot = cache.begin_list();
+        let payload_length = self.as_ref().map_or(0, |v| v.cache_lengths(cache));
+        cache.end_list(slot, payload_length)
+    }
+
+    fn encode_from_cache(&self, cache: &mut LengthCache, out: &mut dyn BufMut) {
+        cache.list_header().encode(out);
+        if let Some(v) = self {
+            v.encode_from_cache(cache, out);
+        }
+    }
+}
+
+unsafe impl<T: MaxEncodedLenAssoc> MaxEncodedLenAssoc for Option<T> {
+    const LEN: usize = length_of_length(T::LEN) + T::LEN;
+}
+
+macro_rules! tuple_impl {
+    ($($name:ident)+) => {
+        /// Tuples are encoded as a list of their elements.
+        impl<$($name: Encodable),+> Encodable for ($($name,)+) {
+            fn length(&self) -> usize {
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                let payload_length = 0 $(+ $name.length())+;
+                length_of_length(payload_length) + payload_length
+            }
+
+            fn encode(&self, out: &mut dyn BufMut) {
+                self.encode_to(out)
+            }
+
+            fn encode_to<Buf: BufMut + ?Sized>(&self, out: &mut Buf) {
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                Header {
+                    list: true,
+                    payload_length: 0 $(+ $name.length())+,
+                }
+                .encode(out);
+                $($name.encode_to(out);)+
+            }
+
+            fn cache_lengths(&self, cache: &mut LengthCache) -> usize {
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                let slot = cache.begin_list();
+                let payload_length = 0 $(+ $name.cache_lengths(cache))+;
+                cache.end_list(slot, payload_length)
+            }
+
+            fn encode_from_cache(&self, cache: &mut LengthCache, out: &mut dyn BufMut) {
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                cache.list_header().encode(out);
+                $($name.encode_from_cache(cache, out);)+
+            }
+        }
+
+        unsafe impl<$($name: MaxEncodedLenAssoc),+> MaxEncodedLenAssoc for ($($name,)+) {
+            const LEN: usize = length_of_length(0 $(+ $name::LEN)+) $(+ $name::LEN)+;
+        }
+    };
+}
+
+tuple_impl!(A);
+tuple_impl!(A B);
+tuple_impl!(A B C);
+tuple_impl!(A B C D);
+tuple_impl!(A B C D E);
+tuple_impl!(A B C D E F);
+tuple_impl!(A B C D E F G);
+tuple_impl!(A B C D E F G H);
+tuple_impl!(A B C D E F G H I);
+tuple_impl!(A B C D E F G H I J);
+tuple_impl!(A B C D E F G H I J K);
+tuple_impl!(A B C D E F G H I J K L);
+
 fn rlp_list_header<E, K>(v: &[K]) -> Header
 where
     E: Encodable + ?Sized,
@@ -322,6 +586,9 @@ where
     length_of_length(payload_length) + payload_length
 }
 
+/// Encodes the items of `v` as a list. `E` may be unsized, so the items are
+/// written with [`Encodable::encode`]: encode a `Vec` with
+/// [`Encodable::encode_to`] for static dispatch.
 pub fn encode_list<E, K>(v: &[K], out: &mut dyn BufMut)
 where
     E: Encodable + ?Sized,
@@ -334,12 +601,57 @@ where
     }
 }
 
+/// [`list_length`] of the items of `iter`, such as a mapped or filtered
+/// view of a collection.
+pub fn iter_length<I>(iter: I) -> usize
+where
+    I: IntoIterator,
+    I::Item: Encodable,
+{
+    let payload_length = iter.into_iter().map(|x| x.length()).sum();
+    length_of_length(payload_length) + payload_length
+}
+
+/// [`encode_list`] of the items of `iter`, without collecting them. `iter`
+/// is cloned to compute the header, so the items are visited twice. Items are
+/// written with [`Encodable::encode_to`].
+pub fn encode_iter<I, B>(iter: I, out: &mut B)
+where
+    I: IntoIterator + Clone,
+    I::Item: Encodable,
+    B: BufMut + ?Sized,
+{
+    Header {
+        list: true,
+        payload_length: iter.clone().into_iter().map(|x| x.length()).sum(),
+    }
+    .encode(out);
+    for x in iter {
+        x.encode_to(out);
+    }
+}
+
+/// Encodes `v` on the stack.
+///
+/// # Panics
+/// If `v` is longer than its `MaxEncodedLen` bound. Debug builds check this
+/// before encoding, to name the type with the wrong bound.
 pub fn encode_fixed_size<E: MaxEncodedLen<LEN>, const LEN: usize>(v: &E) -> ArrayVec<u8, LEN> {
+    #[cfg(debug_assertions)]
+    if let Err(EncodeError::BufferTooSmall { length, .. }) = try_encode_fixed_size::<E, LEN>(v) {
+        panic!(
+            "{} encodes to {} bytes, more than its MaxEncodedLen of {}",
+            core::any::type_name::<E>(),
+            length,
+            LEN
+        );
+    }
+
     let mut out = ArrayVec::from([0_u8; LEN]);
 
     let mut s = out.as_mut_slice();
 
-    v.encode(&mut s);
+    v.encode_to(&mut s);
 
     let final_len = LEN - s.len();
     out.truncate(final_len);
@@ -347,6 +659,96 @@ pub fn encode_fixed_size<E: MaxEncodedLen<LEN>, const LEN: usize>(v: &E) -> Arra
     out
 }
 
+/// Encodes `v` on the stack, failing if it is longer than `LEN`. Unlike
+/// [`encode_fixed_size`], `v` needs no `MaxEncodedLen` bound. The length is
+/// that of the bytes actually written, whatever [`Encodable::length`] says.
+pub fn try_encode_fixed_size<E: Encodable, const LEN: usize>(
+    v: &E,
+) -> Result<ArrayVec<u8, LEN>, EncodeError> {
+    let mut out = ArrayVec::from([0_u8; LEN]);
+    let mut buf = BoundedBuf::new(&mut out);
+    v.encode_to(&mut buf);
+    let length = buf.len;
+    if length > LEN {
+        return Err(EncodeError::BufferTooSmall {
+            length,
+            capacity: LEN,
+        });
+    }
+    out.truncate(length);
+
+    Ok(out)
+}
+
+/// [`BufMut`] writing into a slice, which counts the bytes put past its end
+/// instead of panicking.
+struct BoundedBuf<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    // Handed out by `chunk_mut` once the slice is full.
+    scratch: [u8; 32],
+}
+
+impl<'a> BoundedBuf<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            len: 0,
+            scratch: [0; 32],
+        }
+    }
+}
+
+unsafe impl BufMut for BoundedBuf<'_> {
+    fn remaining_mut(&self) -> usize {
+        usize::MAX - self.len
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        self.len += cnt;
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        match self.buf.get_mut(self.len..) {
+            Some(rest) if !rest.is_empty() => UninitSlice::new(rest),
+            _ => UninitSlice::new(&mut self.scratch),
+        }
+    }
+
+    fn put_slice(&mut self, src: &[u8]) {
+        if let Some(rest) = self.buf.get_mut(self.len..) {
+            let n = rest.len().min(src.len());
+            rest[..n].copy_from_slice(&src[..n]);
+        }
+        self.len += src.len();
+    }
+
+    fn put_u8(&mut self, n: u8) {
+        if let Some(b) = self.buf.get_mut(self.len) {
+            *b = n;
+        }
+        self.len += 1;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodeError {
+    BufferTooSmall { length: usize, capacity: usize },
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::BufferTooSmall { length, capacity } => {
+                write!(f, "encoding of {length} bytes exceeds buffer of {capacity}")
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     extern crate alloc;
@@ -359,6 +761,15 @@ mod tests {
     fn encoded<T: Encodable>(t: T) -> BytesMut {
         let mut out = BytesMut::new();
         t.encode(&mut out);
+
+        let mut out_to = BytesMut::new();
+        t.encode_to(&mut out_to);
+        assert_eq!(out_to, out);
+
+        let mut out_cached = BytesMut::new();
+        crate::encode_cached(&t, &mut out_cached);
+        assert_eq!(out_cached, out);
+
         out
     }
 
@@ -373,6 +784,10 @@ mod tests {
         v.encode(&mut out2);
         assert_eq!(out1, out2);
 
+        let mut out3 = BytesMut::new();
+        crate::encode_list_cached(t, &mut out3);
+        assert_eq!(out1, out3);
+
         out1
     }
 
@@ -465,4 +880,215 @@ mod tests {
             &hex!("c883ffccb583ffc0b5")[..]
         );
     }
+
+    #[test]
+    fn rlp_option() {
+        assert_eq!(encoded(None::<u64>)[..], hex!("c0")[..]);
+        assert_eq!(encoded(Some(0_u64))[..], hex!("c180")[..]);
+        assert_eq!(encoded(Some(0xFFCCB5_u64))[..], hex!("c483ffccb5")[..]);
+        assert_eq!(Some(0xFFCCB5_u64).length(), 5);
+        assert_eq!(<Option<u64> as MaxEncodedLenAssoc>::LEN, 10);
+    }
+
+    #[test]
+    fn rlp_tuples() {
+        assert_eq!(encoded((1_u8,))[..], hex!("c101")[..]);
+        assert_eq!(
+            encoded((0xFFCCB5_u64, true, &hex!("ABBA")[..]))[..],
+            hex!("c883ffccb50182abba")[..]
+        );
+        assert_eq!((0xFFCCB5_u64, true, &hex!("ABBA")[..]).length(), 9);
+        assert_eq!(
+            <(u8, u64, [u8; 4]) as MaxEncodedLenAssoc>::LEN,
+            1 + 2 + 9 + 5
+        );
+    }
+
+    #[test]
+    fn length_counter() {
+        // Relies on the default `length`.
+        struct Pair(u64, &'static [u8]);
+
+        impl Encodable for Pair {
+            fn encode(&self, out: &mut dyn BufMut) {
+                Header {
+                    list: true,
+                    payload_length: self.0.length() + self.1.length(),
+                }
+                .encode(out);
+                self.0.encode(out);
+                self.1.encode(out);
+            }
+        }
+
+        let pair = Pair(0xFFCCB5, &[0xAB; 60]);
+        assert_eq!(pair.length(), encoded(&pair).len());
+        assert_eq!(pair.length(), 68);
+
+        let mut counter = LengthCounter::new();
+        assert!(counter.is_empty());
+        counter.put_u8(1);
+        counter.put_slice(&[0; 100]);
+        counter.put_bytes(0, 100);
+        counter.put(&[0_u8; 100][..]);
+        counter.put_u64(0);
+        assert_eq!(counter.len(), 309);
+    }
+
+    #[test]
+    fn rlp_trait_objects() {
+        let items: [&dyn Encodable; 3] = [&0xFFCCB5_u64, &true, &hex!("ABBA")];
+        let mut out = BytesMut::new();
+        for item in items {
+            item.encode(&mut out);
+            (&item).encode_to(&mut out);
+        }
+        assert_eq!(out[..], hex!("83ffccb583ffccb5010182abba82abba")[..]);
+
+        let boxed: alloc::boxed::Box<dyn Encodable> = alloc::boxed::Box::new(vec![1_u8, 2]);
+        assert_eq!(encoded(&boxed)[..], hex!("c20102")[..]);
+        assert_eq!(boxed.length(), 3);
+
+        let shared: alloc::sync::Arc<dyn Encodable + Send + Sync> = alloc::sync::Arc::new(true);
+        assert_eq!(
+            encoded(vec![shared.clone(), shared])[..],
+            hex!("c20101")[..]
+        );
+
+        // Trait objects of subtraits are encodable too.
+        trait Item: Encodable {}
+        impl Item for u8 {}
+        let item: &dyn Item = &5_u8;
+        assert_eq!(encoded(item)[..], hex!("05")[..]);
+
+        let synced: alloc::boxed::Box<dyn Encodable + Sync> = alloc::boxed::Box::new(5_u8);
+        assert_eq!(encoded(&synced)[..], hex!("05")[..]);
+
+        let (a, b) = (0xFFCCB5_u64, 0x0400_u64);
+        assert_eq!(encoded(vec![&a, &b])[..], hex!("c783ffccb5820400")[..]);
+        assert_eq!(
+            encoded(alloc::boxed::Box::new(&a))[..],
+            hex!("83ffccb5")[..]
+        );
+    }
+
+    #[test]
+    fn rlp_length_cache() {
+        use core::cell::Cell;
+
+        // Counts the calls to `length`.
+        struct Counted<'a>(u64, &'a Cell<usize>);
+
+        impl Encodable for Counted<'_> {
+            fn length(&self) -> usize {
+                self.1.set(self.1.get() + 1);
+                self.0.length()
+            }
+
+            fn encode(&self, out: &mut dyn BufMut) {
+                self.0.encode(out)
+            }
+        }
+
+        let calls = Cell::new(0);
+        let v = (0..3)
+            .map(|i| {
+                (0..3)
+                    .map(|j| vec![Counted(i * 3 + j, &calls), Counted(0x400, &calls)])
+                    .collect::<alloc::vec::Vec<_>>()
+            })
+            .collect::<alloc::vec::Vec<_>>();
+
+        let mut out = BytesMut::new();
+        v.encode(&mut out);
+        // Once per enclosing list.
+        assert_eq!(calls.get(), 3 * 18);
+
+        calls.set(0);
+        let mut out_cached = BytesMut::new();
+        crate::encode_cached(&v, &mut out_cached);
+        assert_eq!(out_cached, out);
+        assert_eq!(calls.get(), 18);
+    }
+
+    #[test]
+    fn rlp_iter() {
+        let v = [0xFFCCB5_u64, 0, 0xFFC0B5, 7];
+        let mut out = BytesMut::new();
+        encode_iter(v.iter().filter(|&&x| x != 0), &mut out);
+        assert_eq!(out[..], encoded_list(&[0xFFCCB5_u64, 0xFFC0B5, 7])[..]);
+        assert_eq!(iter_length(v.iter().filter(|&&x| x != 0)), out.len());
+
+        let mut out = BytesMut::new();
+        encode_iter(v.iter().map(|x| x.to_be_bytes()), &mut out);
+        assert_eq!(out.len(), 1 + 4 * 9);
+        assert_eq!(iter_length(v.iter().map(|x| x.to_be_bytes())), out.len());
+
+        let mut out = BytesMut::new();
+        encode_iter(0..0_u64, &mut out);
+        assert_eq!(out[..], hex!("c0")[..]);
+        assert_eq!(iter_length(0..0_u64), 1);
+    }
+
+    #[test]
+    fn rlp_try_encode_fixed_size() {
+        let out = try_encode_fixed_size::<_, 9>(&0xFFCCB5DDFFEE1483_u64).unwrap();
+        assert_eq!(out[..], hex!("88ffccb5ddffee1483")[..]);
+        let out = try_encode_fixed_size::<_, 16>(&vec![1_u8, 2]).unwrap();
+        assert_eq!(out[..], hex!("c20102")[..]);
+        assert_eq!(
+            try_encode_fixed_size::<_, 8>(&0xFFCCB5DDFFEE1483_u64),
+            Err(EncodeError::BufferTooSmall {
+                length: 9,
+                capacity: 8
+            })
+        );
+
+        // Only the bytes written count, not what `length` claims.
+        struct WrongLength(usize);
+        impl Encodable for WrongLength {
+            fn encode(&self, out: &mut dyn BufMut) {
+                0xFFCCB5_u64.encode(out)
+            }
+            fn length(&self) -> usize {
+                self.0
+            }
+        }
+        for claimed in [0, 2, 8] {
+            let out = try_encode_fixed_size::<_, 6>(&WrongLength(claimed)).unwrap();
+            assert_eq!(out[..], hex!("83ffccb5")[..]);
+            assert_eq!(
+                try_encode_fixed_size::<_, 3>(&WrongLength(claimed)),
+                Err(EncodeError::BufferTooSmall {
+                    length: 4,
+                    capacity: 3
+                })
+            );
+        }
+    }
+
+    #[test]
+    #[cfg_attr(
+        debug_assertions,
+        should_panic(expected = "TooLong encodes to 4 bytes, more than its MaxEncodedLen of 2")
+    )]
+    #[cfg_attr(not(debug_assertions), should_panic)]
+    fn rlp_encode_fixed_size_wrong_bound() {
+        // Declares a bound too small for its encoding.
+        struct TooLong;
+
+        impl Encodable for TooLong {
+            fn encode(&self, out: &mut dyn BufMut) {
+                0xFFCCB5_u64.encode(out)
+            }
+
+            fn length(&self) -> usize {
+                2
+            }
+        }
+
+        unsafe impl MaxEncodedLen<2> for TooLong {}
+
+        encode_fixed_size(&TooLong);
+    }
 }
@@ -0,0 +1,169 @@
+//! Encoding of `[T; N]` as a list of `N` elements, such as the `[H256; 16]`
+//! children of a trie branch node.
+//!
+//! `[u8; N]` already implements [`Encodable`] as a byte string, so arrays of
+//! other types cannot get their own impl. Use these functions directly, on a
+//! derived struct field with `#[rlp(with = "fastrlp::fixed_list")]`, or wrap
+//! the array in a [`FixedList`], which also has a `MaxEncodedLenAssoc` bound.
+
+use crate::{
+    encode_list, length_cache::list_cache_lengths, length_of_length, list_length, types::Header,
+    BorrowDecodable, Decodable, DecodeError, Encodable, LengthCache, MaxEncodedLenAssoc,
+};
+use arrayvec::ArrayVec;
+use bytes::{Buf, BufMut};
+use core::ops::{Deref, DerefMut};
+
+pub fn encode<T: Encodable, const N: usize>(value: &[T; N], out: &mut dyn BufMut) {
+    encode_list::<T, T>(value, out)
+}
+
+pub fn length<T: Encodable, const N: usize>(value: &[T; N]) -> usize {
+    list_length::<T, T>(value)
+}
+
+pub fn decode<T: Decodable, const N: usize>(buf: &mut &[u8]) -> Result<[T; N], DecodeError> {
+    let h = Header::decode(buf)?;
+    if !h.list {
+        return Err(DecodeError::UnexpectedString);
+    }
+
+    let payload_view = &mut &buf[..h.payload_length];
+
+    let mut to = ArrayVec::<T, N>::new();
+    while !payload_view.is_empty() {
+        to.try_push(T::decode(payload_view)?)
+            .map_err(|_| DecodeError::UnexpectedLength)?;
+    }
+
+    buf.advance(h.payload_length);
+
+    to.into_inner().map_err(|_| DecodeError::UnexpectedLength)
+}
+
+/// `[T; N]` encoded as a list of `N` elements, for structs that need a type
+/// rather than a `with` module, such as `#[derive(RlpMaxEncodedLen)]` ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FixedList<T, const N: usize>(pub [T; N]);
+
+impl<T, const N: usize> From<[T; N]> for FixedList<T, N> {
+    fn from(value: [T; N]) -> Self {
+        Self(value)
+    }
+}
+
+impl<T, const N: usize> Deref for FixedList<T, N> {
+    type Target = [T; N];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T, const N: usize> DerefMut for FixedList<T, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: Encodable, const N: usize> Encodable for FixedList<T, N> {
+    fn length(&self) -> usize {
+        length(&self.0)
+    }
+
+    fn encode(&self, out: &mut dyn BufMut) {
+        self.encode_to(out)
+    }
+
+    fn encode_to<B: BufMut + ?Sized>(&self, out: &mut B) {
+        Header {
+            list: true,
+            payload_length: self.0.iter().map(Encodable::length).sum(),
+        }
+        .encode(out);
+        for x in &self.0 {
+            x.encode_to(out);
+        }
+    }
+
+    fn cache_lengths(&self, cache: &mut LengthCache) -> usize {
+        let slot = cache.begin_list();
+        let payload_length = list_cache_lengths::<T, T>(&self.0, cache);
+        cache.end_list(slot, payload_length)
+    }
+
+    fn encode_from_cache(&self, cache: &mut LengthCache, out: &mut dyn BufMut) {
+        cache.list_header().encode(out);
+        for x in &self.0 {
+            x.encode_from_cache(cache, out);
+        }
+    }
+}
+
+unsafe impl<T: MaxEncodedLenAssoc, const N: usize> MaxEncodedLenAssoc for FixedList<T, N> {
+    const LEN: usize = length_of_length(N * T::LEN) + N * T::LEN;
+}
+
+impl<T: Decodable, const N: usize> Decodable for FixedList<T, N> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        decode(buf).map(Self)
+    }
+}
+
+impl<'de, T: Decodable, const N: usize> BorrowDecodable<'de> for FixedList<T, N> {
+    fn borrow_decode(buf: &mut &'de [u8]) -> Result<Self, DecodeError> {
+        Self::decode(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+    use hex_literal::hex;
+
+    #[test]
+    fn fixed_list() {
+        let v = [1_u64, 0x400, 0];
+        let mut out = BytesMut::new();
+        encode(&v, &mut out);
+        assert_eq!(out[..], hex!("c50182040080")[..]);
+        assert_eq!(length(&v), out.len());
+
+        let mut buf = &out[..];
+        assert_eq!(decode::<u64, 3>(&mut buf), Ok(v));
+        assert!(buf.is_empty());
+
+        assert_eq!(
+            decode::<u64, 2>(&mut &out[..]),
+            Err(DecodeError::UnexpectedLength)
+        );
+        assert_eq!(
+            decode::<u64, 4>(&mut &out[..]),
+            Err(DecodeError::UnexpectedLength)
+        );
+        assert_eq!(decode::<u64, 0>(&mut &hex!("c0")[..]), Ok([]));
+        assert_eq!(
+            decode::<u64, 1>(&mut &hex!("8180")[..]),
+            Err(DecodeError::UnexpectedString)
+        );
+    }
+
+    #[test]
+    fn fixed_list_type() {
+        let v = FixedList([1_u64, 0x400, 0]);
+        let mut out = BytesMut::new();
+        v.encode(&mut out);
+        assert_eq!(out[..], hex!("c50182040080")[..]);
+        assert_eq!(v.length(), out.len());
+        assert_eq!(FixedList::decode(&mut &out[..]), Ok(v));
+        assert_eq!(
+            FixedList::<u64, 2>::decode(&mut &out[..]),
+            Err(DecodeError::UnexpectedLength)
+        );
+
+        assert_eq!(<FixedList<u64, 3> as MaxEncodedLenAssoc>::LEN, 28);
+        let max = FixedList([u64::MAX; 3]);
+        assert_eq!(max.length(), <FixedList<u64, 3> as MaxEncodedLenAssoc>::LEN);
+    }
+}
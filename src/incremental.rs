@@ -0,0 +1,158 @@
+use crate::{decode::decode_item, Decodable, DecodeError, Header, ItemLength};
+use bytes::{Bytes, BytesMut};
+
+/// Splits RLP items out of data received in arbitrary chunks, such as reads
+/// from a socket.
+///
+/// Received data is appended with [`Self::extend_from_slice`], and complete
+/// items are taken with [`Self::next_item`] or [`Self::decode_next`]. An item
+/// longer than `max_item_size` fails with [`DecodeError::ItemTooLarge`] as
+/// soon as its header is received, so check [`Self::needed`] after every read
+/// to avoid buffering it.
+#[derive(Clone, Debug)]
+pub struct IncrementalDecoder {
+    buf: BytesMut,
+    max_item_size: usize,
+}
+
+impl IncrementalDecoder {
+    pub fn new(max_item_size: usize) -> Self {
+        Self {
+            buf: BytesMut::new(),
+            max_item_size,
+        }
+    }
+
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data)
+    }
+
+    /// Data received but not yet taken as items.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Number of bytes that must still be received to complete the next item,
+    /// `0` if it is already complete. Until the item's header is received,
+    /// this is only a lower bound.
+    pub fn needed(&self) -> Result<usize, DecodeError> {
+        Ok(match self.item_length()? {
+            ItemLength::Complete(_) => 0,
+            ItemLength::Incomplete { needed } => needed,
+        })
+    }
+
+    /// Takes the next item, header included, if it has been fully received.
+    pub fn next_item(&mut self) -> Result<Option<Bytes>, DecodeError> {
+        Ok(match self.item_length()? {
+            ItemLength::Complete(length) => Some(self.buf.split_to(length).freeze()),
+            ItemLength::Incomplete { .. } => None,
+        })
+    }
+
+    /// Takes and decodes the next item, if it has been fully received.
+    pub fn decode_next<T: Decodable>(&mut self) -> Result<Option<T>, DecodeError> {
+        self.next_item()?
+            .map(|item| decode_item(item, T::decode_bytes))
+            .transpose()
+    }
+
+    fn item_length(&self) -> Result<ItemLength, DecodeError> {
+        peek_item_length_checked(&self.buf, self.max_item_size)
+    }
+}
+
+/// [`Header::peek_item_length`] that fails once the item is known to be longer
+/// than `max_item_size`.
+pub(crate) fn peek_item_length_checked(
+    buf: &[u8],
+    max_item_size: usize,
+) -> Result<ItemLength, DecodeError> {
+    let item_length = Header::peek_item_length(buf)?;
+    // An incomplete item is at least as long as what is needed to complete it.
+    let length = match item_length {
+        ItemLength::Complete(length) => length,
+        ItemLength::Incomplete { needed } => buf.len().saturating_add(needed),
+    };
+    if length > max_item_size {
+        return Err(DecodeError::ItemTooLarge {
+            length,
+            max: max_item_size,
+        });
+    }
+
+    Ok(item_length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::tests::FirstByte;
+    use hex_literal::hex;
+
+    #[test]
+    fn peek_item_length() {
+        for (buf, expected) in [
+            (&[][..], Ok(ItemLength::Incomplete { needed: 1 })),
+            (&hex!("7b")[..], Ok(ItemLength::Complete(1))),
+            (&hex!("7b7b")[..], Ok(ItemLength::Complete(1))),
+            (&hex!("81")[..], Ok(ItemLength::Incomplete { needed: 1 })),
+            (&hex!("8180")[..], Ok(ItemLength::Complete(2))),
+            (&hex!("8100")[..], Err(DecodeError::NonCanonicalSingleByte)),
+            (&hex!("83ab")[..], Ok(ItemLength::Incomplete { needed: 2 })),
+            (&hex!("b9")[..], Ok(ItemLength::Incomplete { needed: 1 })),
+            (&hex!("b901")[..], Ok(ItemLength::Incomplete { needed: 1 })),
+            (
+                &hex!("b90100")[..],
+                Ok(ItemLength::Incomplete { needed: 256 }),
+            ),
+            (&hex!("b90010")[..], Err(DecodeError::LeadingZero)),
+            (&hex!("c3")[..], Ok(ItemLength::Incomplete { needed: 3 })),
+            (&hex!("c3010203")[..], Ok(ItemLength::Complete(4))),
+        ] {
+            assert_eq!(Header::peek_item_length(buf), expected);
+        }
+    }
+
+    #[test]
+    fn incremental_decoder() {
+        let mut decoder = IncrementalDecoder::new(16);
+        assert_eq!(decoder.needed(), Ok(1));
+        assert_eq!(decoder.next_item(), Ok(None));
+
+        decoder.extend_from_slice(&hex!("c3"));
+        assert_eq!(decoder.needed(), Ok(3));
+        decoder.extend_from_slice(&hex!("0102"));
+        assert_eq!(decoder.needed(), Ok(1));
+        assert_eq!(decoder.decode_next::<Vec<u8>>(), Ok(None));
+
+        decoder.extend_from_slice(&hex!("03820400"));
+        assert_eq!(decoder.needed(), Ok(0));
+        assert_eq!(decoder.decode_next(), Ok(Some(vec![1_u8, 2, 3])));
+        assert_eq!(
+            decoder.next_item(),
+            Ok(Some(Bytes::from_static(&hex!("820400"))))
+        );
+        assert_eq!(decoder.next_item(), Ok(None));
+        assert!(decoder.buffered().is_empty());
+
+        // Values must be the whole item.
+        decoder.extend_from_slice(&hex!("820400"));
+        assert!(matches!(
+            decoder.decode_next::<FirstByte>(),
+            Err(DecodeError::ListLengthMismatch {
+                expected: 3,
+                got: 1
+            })
+        ));
+
+        decoder.extend_from_slice(&hex!("b90100"));
+        assert_eq!(
+            decoder.needed(),
+            Err(DecodeError::ItemTooLarge {
+                length: 259,
+                max: 16
+            })
+        );
+    }
+}
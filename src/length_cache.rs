@@ -0,0 +1,119 @@
+use crate::{length_of_length, types::Header, Encodable};
+use bytes::BufMut;
+use core::borrow::Borrow;
+
+/// Number of lists a [`LengthCache`] holds without the `alloc` feature.
+/// Values with more lists are encoded without the cache.
+pub const MAX_CACHED_LISTS: usize = 64;
+
+#[cfg(feature = "alloc")]
+type Lengths = alloc::vec::Vec<usize>;
+#[cfg(not(feature = "alloc"))]
+type Lengths = arrayvec::ArrayVec<usize, MAX_CACHED_LISTS>;
+
+/// Payload lengths of the lists of a value, computed once by
+/// [`Encodable::cache_lengths`] and read back by
+/// [`Encodable::encode_from_cache`].
+///
+/// Plain [`Encodable::encode`] asks every list for its length to write its
+/// header, and every nested list again for each enclosing one, so that
+/// encoding time grows with the nesting depth. Use [`encode_cached`] and
+/// [`encode_list_cached`] instead to compute each length once. The output is
+/// the same.
+///
+/// Lists are recorded in the order their headers are written: a list
+/// reserves its slot with [`Self::begin_list`] before caching its elements,
+/// and fills it with [`Self::end_list`].
+#[derive(Clone, Debug, Default)]
+pub struct LengthCache {
+    lengths: Lengths,
+    next: usize,
+    overflowed: bool,
+}
+
+impl LengthCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves the slot of a list whose elements are cached next.
+    pub fn begin_list(&mut self) -> usize {
+        let slot = self.lengths.len();
+        #[cfg(feature = "alloc")]
+        self.lengths.push(0);
+        #[cfg(not(feature = "alloc"))]
+        if self.lengths.try_push(0).is_err() {
+            self.overflowed = true;
+        }
+        slot
+    }
+
+    /// Records the payload length of the list started at `slot`, and returns
+    /// the list's encoded length.
+    pub fn end_list(&mut self, slot: usize, payload_length: usize) -> usize {
+        if let Some(length) = self.lengths.get_mut(slot) {
+            *length = payload_length;
+        }
+        length_of_length(payload_length) + payload_length
+    }
+
+    /// Header of the next list to encode.
+    ///
+    /// # Panics
+    /// If more lists are encoded than were cached.
+    pub fn list_header(&mut self) -> Header {
+        let payload_length = *self
+            .lengths
+            .get(self.next)
+            .expect("list lengths must be cached before encoding");
+        self.next += 1;
+        Header {
+            list: true,
+            payload_length,
+        }
+    }
+}
+
+/// [`Encodable::encode`] computing the length of each nested list once.
+pub fn encode_cached<T: Encodable + ?Sized>(value: &T, out: &mut dyn BufMut) {
+    let mut cache = LengthCache::new();
+    value.cache_lengths(&mut cache);
+    if cache.overflowed {
+        value.encode(out)
+    } else {
+        value.encode_from_cache(&mut cache, out)
+    }
+}
+
+/// [`crate::encode_list`] computing the length of each nested list once.
+pub fn encode_list_cached<E, K>(v: &[K], out: &mut dyn BufMut)
+where
+    E: Encodable + ?Sized,
+    K: Borrow<E>,
+{
+    let mut cache = LengthCache::new();
+    let payload_length = list_cache_lengths::<E, K>(v, &mut cache);
+    if cache.overflowed {
+        crate::encode_list::<E, K>(v, out);
+        return;
+    }
+
+    Header {
+        list: true,
+        payload_length,
+    }
+    .encode(out);
+    for x in v {
+        x.borrow().encode_from_cache(&mut cache, out);
+    }
+}
+
+/// Caches the lengths of the elements of a list, and returns its payload
+/// length.
+pub(crate) fn list_cache_lengths<E, K>(v: &[K], cache: &mut LengthCache) -> usize
+where
+    E: Encodable + ?Sized,
+    K: Borrow<E>,
+{
+    v.iter().map(|x| x.borrow().cache_lengths(cache)).sum()
+}
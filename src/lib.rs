@@ -3,17 +3,43 @@
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
+pub mod byte_vec;
+#[cfg(feature = "tokio-codec")]
+mod codec;
 mod decode;
 mod encode;
+pub mod fixed_list;
+mod incremental;
+mod length_cache;
+mod node;
+#[cfg(feature = "alloc")]
+mod rlp_stream;
+#[cfg(feature = "std")]
+mod stream;
 mod types;
 
-pub use bytes::BufMut;
+pub use bytes::{BufMut, Bytes};
 
-pub use decode::{Decodable, DecodeError, Rlp};
+#[cfg(feature = "tokio-codec")]
+pub use codec::RlpCodec;
+pub use decode::{
+    decode_bytes_with, split_list_payload, BorrowDecodable, Decodable, DecodableFields,
+    DecodeError, DetailedDecodeError, ItemLength, PathSegment, Rlp, MAX_ERROR_PATH_DEPTH,
+};
 pub use encode::{
-    const_add, encode_fixed_size, encode_list, length_of_length, list_length, Encodable,
-    MaxEncodedLen, MaxEncodedLenAssoc,
+    const_add, encode_fixed_size, encode_iter, encode_list, iter_length, length_of_length,
+    list_length, try_encode_fixed_size, Encodable, EncodableFields, EncodeError, LengthCounter,
+    MaxEncodedFieldsLen, MaxEncodedLen, MaxEncodedLenAssoc,
 };
+pub use fixed_list::FixedList;
+pub use incremental::IncrementalDecoder;
+pub use length_cache::{encode_cached, encode_list_cached, LengthCache, MAX_CACHED_LISTS};
+pub use node::{RlpNode, RlpNodeIter};
+#[cfg(feature = "alloc")]
+pub use rlp_stream::RlpStream;
+#[cfg(feature = "std")]
+pub use stream::{RlpReader, RlpWriter};
 pub use types::*;
 
 #[cfg(feature = "derive")]
@@ -0,0 +1,189 @@
+use crate::{types::Header, Decodable, DecodeError};
+
+/// Zero-copy view of an RLP item and, for lists, its children. Nothing is
+/// decoded until asked for, so nested items can be reached without decoding
+/// their siblings: `node.at(8)?.at(2)?`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RlpNode<'a> {
+    raw: &'a [u8],
+    header_length: usize,
+    list: bool,
+    offset: usize,
+}
+
+impl<'a> RlpNode<'a> {
+    /// View of the item at the start of `buf`. Bytes after it are ignored.
+    pub fn new(buf: &'a [u8]) -> Result<Self, DecodeError> {
+        Self::parse(buf, 0)
+    }
+
+    fn parse(buf: &'a [u8], offset: usize) -> Result<Self, DecodeError> {
+        let mut payload = buf;
+        let h = Header::decode(&mut payload)?;
+        let header_length = buf.len() - payload.len();
+
+        Ok(Self {
+            raw: &buf[..header_length + h.payload_length],
+            header_length,
+            list: h.list,
+            offset,
+        })
+    }
+
+    pub fn is_list(&self) -> bool {
+        self.list
+    }
+
+    pub fn is_string(&self) -> bool {
+        !self.list
+    }
+
+    /// The item's payload: the bytes of a string, or the encoded items of a
+    /// list.
+    pub fn payload(&self) -> &'a [u8] {
+        &self.raw[self.header_length..]
+    }
+
+    /// The whole encoded item, header included.
+    pub fn raw(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    /// Position of the item's header in the buffer passed to [`Self::new`].
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Iterates over the items of a list.
+    pub fn children(&self) -> Result<RlpNodeIter<'a>, DecodeError> {
+        if !self.list {
+            return Err(DecodeError::UnexpectedString);
+        }
+
+        Ok(RlpNodeIter {
+            remaining: self.payload(),
+            offset: self.offset + self.header_length,
+        })
+    }
+
+    /// Number of items in a list. Only their headers are read.
+    pub fn item_count(&self) -> Result<usize, DecodeError> {
+        self.children()?.try_fold(0, |count, child| {
+            child?;
+            Ok(count + 1)
+        })
+    }
+
+    /// The `index`-th item of a list, or `None` if the list has fewer items.
+    pub fn at(&self, index: usize) -> Result<Option<Self>, DecodeError> {
+        self.children()?.nth(index).transpose()
+    }
+
+    /// Decodes the item.
+    pub fn decode<T: Decodable>(&self) -> Result<T, DecodeError> {
+        T::decode(&mut self.raw())
+    }
+}
+
+/// Iterator over the items of a list, returned by [`RlpNode::children`]. It
+/// stops after the first malformed item.
+#[derive(Clone, Debug)]
+pub struct RlpNodeIter<'a> {
+    remaining: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Iterator for RlpNodeIter<'a> {
+    type Item = Result<RlpNode<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining.is_empty() {
+            return None;
+        }
+
+        match RlpNode::parse(self.remaining, self.offset) {
+            Ok(node) => {
+                self.remaining = &self.remaining[node.raw.len()..];
+                self.offset += node.raw.len();
+                Some(Ok(node))
+            }
+            Err(err) => {
+                self.remaining = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use super::*;
+    use alloc::vec::Vec;
+    use hex_literal::hex;
+
+    #[test]
+    fn rlp_node() {
+        // [["cat", "dog"], 0x7b, [], "aaa...a" (56 bytes)]
+        let mut data = hex!("f845c88363617483646f677bc0b838").to_vec();
+        data.extend_from_slice(&[b'a'; 56]);
+        let node = RlpNode::new(&data).unwrap();
+
+        assert!(node.is_list());
+        assert_eq!(node.offset(), 0);
+        assert_eq!(node.raw(), &data[..]);
+        assert_eq!(node.payload(), &data[2..]);
+        assert_eq!(node.item_count(), Ok(4));
+
+        let animals = node.at(0).unwrap().unwrap();
+        assert_eq!(animals.offset(), 2);
+        assert_eq!(animals.item_count(), Ok(2));
+        let dog = animals.at(1).unwrap().unwrap();
+        assert!(dog.is_string());
+        assert_eq!(dog.offset(), 7);
+        assert_eq!(dog.payload(), b"dog");
+        assert_eq!(dog.children().unwrap_err(), DecodeError::UnexpectedString);
+
+        let single = node.at(1).unwrap().unwrap();
+        assert_eq!(single.offset(), 11);
+        assert_eq!(single.raw(), &hex!("7b"));
+        assert_eq!(single.payload(), &hex!("7b"));
+        assert_eq!(single.decode::<u8>(), Ok(0x7b));
+
+        let empty = node.at(2).unwrap().unwrap();
+        assert!(empty.is_list());
+        assert_eq!(empty.item_count(), Ok(0));
+        assert_eq!(empty.at(0), Ok(None));
+        assert_eq!(node.at(4), Ok(None));
+        assert_eq!(dog.at(0), Err(DecodeError::UnexpectedString));
+
+        let long = node.at(3).unwrap().unwrap();
+        assert_eq!(long.offset(), 13);
+        assert_eq!(long.payload(), &[b'a'; 56]);
+
+        let offsets: Vec<_> = node
+            .children()
+            .unwrap()
+            .map(|child| child.unwrap().offset())
+            .collect();
+        assert_eq!(offsets, [2, 11, 12, 13]);
+    }
+
+    #[test]
+    fn rlp_node_malformed() {
+        assert_eq!(
+            RlpNode::new(&hex!("c30183")),
+            Err(DecodeError::InputTooShort)
+        );
+
+        // The second item claims more bytes than the list holds.
+        let data = hex!("c401830102ff");
+        let node = RlpNode::new(&data).unwrap();
+        let mut children = node.children().unwrap();
+        assert_eq!(children.next().unwrap().unwrap().payload(), &hex!("01"));
+        assert_eq!(children.next(), Some(Err(DecodeError::InputTooShort)));
+        assert_eq!(children.next(), None);
+        assert_eq!(node.item_count(), Err(DecodeError::InputTooShort));
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{length_of_length, types::Header, Encodable};
+use alloc::vec::Vec;
+use bytes::{BufMut, BytesMut};
+
+/// Builds an encoding item by item, for structures whose lengths are awkward
+/// to compute ahead of writing them.
+///
+/// List headers don't have to be known when a list is started: one byte is
+/// reserved for the header, and [`Self::finish_list`] writes it once the
+/// payload is complete, moving the payload if the header needs more room.
+/// Payloads of 56 bytes or more are moved once per enclosing list, so prefer
+/// [`Encodable`] impls for large, deeply nested values.
+///
+/// ```
+/// # use fastrlp::RlpStream;
+/// let mut stream = RlpStream::new();
+/// stream.begin_list().append(&1_u64).begin_list().finish_list();
+/// stream.append(&"cat".to_string()).finish_list();
+/// assert_eq!(stream.out()[..], [0xc6, 0x01, 0xc0, 0x83, b'c', b'a', b't']);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RlpStream {
+    buf: BytesMut,
+    // Payload start of every unfinished list, innermost last.
+    open_lists: Vec<usize>,
+}
+
+impl RlpStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buf: BytesMut::with_capacity(capacity),
+            open_lists: Vec::new(),
+        }
+    }
+
+    /// Starts a list, which the next items are appended to until
+    /// [`Self::finish_list`].
+    pub fn begin_list(&mut self) -> &mut Self {
+        self.buf.put_u8(0);
+        self.open_lists.push(self.buf.len());
+        self
+    }
+
+    /// Writes the header of the innermost unfinished list.
+    ///
+    /// # Panics
+    /// If there is no unfinished list.
+    pub fn finish_list(&mut self) -> &mut Self {
+        let start = self
+            .open_lists
+            .pop()
+            .expect("finish_list called without an unfinished list");
+        let payload_length = self.buf.len() - start;
+        let header_length = length_of_length(payload_length);
+        let header_start = start - 1;
+        if header_length > 1 {
+            let extra = header_length - 1;
+            self.buf.resize(self.buf.len() + extra, 0);
+            self.buf
+                .copy_within(start..start + payload_length, start + extra);
+        }
+
+        Header {
+            list: true,
+            payload_length,
+        }
+        .encode(&mut &mut self.buf[header_start..header_start + header_length]);
+        self
+    }
+
+    pub fn append<T: Encodable>(&mut self, value: &T) -> &mut Self {
+        value.encode_to(&mut self.buf);
+        self
+    }
+
+    /// Appends bytes that are already encoded, such as a cached item.
+    pub fn append_raw(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(bytes);
+        self
+    }
+
+    /// Whether every list started has been finished.
+    pub fn is_finished(&self) -> bool {
+        self.open_lists.is_empty()
+    }
+
+    /// The bytes written so far. Unfinished lists have a placeholder header.
+    pub fn as_raw(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// The encoded items.
+    ///
+    /// # Panics
+    /// If a list is unfinished.
+    pub fn out(self) -> BytesMut {
+        assert!(self.is_finished(), "RlpStream has unfinished lists");
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use bytes::Bytes;
+    use hex_literal::hex;
+
+    fn encoded<T: Encodable>(t: &T) -> BytesMut {
+        let mut out = BytesMut::new();
+        t.encode(&mut out);
+        out
+    }
+
+    #[test]
+    fn rlp_stream() {
+        let mut stream = RlpStream::new();
+        stream
+            .begin_list()
+            .append(&0xFFCCB5_u64)
+            .begin_list()
+            .append(&Bytes::from_static(b"dog"))
+            .append_raw(&hex!("c0"))
+            .finish_list()
+            .append(&true)
+            .finish_list();
+        assert_eq!(
+            stream.out(),
+            encoded(&(
+                0xFFCCB5_u64,
+                (Bytes::from_static(b"dog"), Vec::<u8>::new()),
+                true
+            ))
+        );
+
+        // Items outside of lists are written back to back.
+        let mut stream = RlpStream::new();
+        stream
+            .append(&1_u8)
+            .begin_list()
+            .finish_list()
+            .append(&2_u8);
+        assert!(stream.is_finished());
+        assert_eq!(stream.out()[..], hex!("01c002")[..]);
+    }
+
+    #[test]
+    fn rlp_stream_long_lists() {
+        // The inner list needs a 2-byte header, and the outer one a 3-byte
+        // header, so both payloads are moved.
+        let inner = vec![0xFFCCB5_u64; 20];
+        let outer = vec![inner.clone(); 4];
+
+        let mut stream = RlpStream::with_capacity(outer.length());
+        stream.begin_list();
+        for inner in &outer {
+            stream.begin_list();
+            for x in inner {
+                stream.append(x);
+            }
+            stream.finish_list();
+        }
+        stream.finish_list();
+        assert_eq!(stream.out(), encoded(&outer));
+
+        let mut stream = RlpStream::new();
+        stream.begin_list().append(&inner);
+        assert!(!stream.is_finished());
+        assert_eq!(stream.as_raw()[0], 0);
+        stream.finish_list();
+        assert_eq!(stream.out(), encoded(&(inner,)));
+    }
+
+    #[test]
+    #[should_panic(expected = "unfinished lists")]
+    fn rlp_stream_unfinished() {
+        let mut stream = RlpStream::new();
+        stream.begin_list().append(&1_u8);
+        stream.out();
+    }
+
+    #[test]
+    #[should_panic(expected = "without an unfinished list")]
+    fn rlp_stream_unbalanced() {
+        RlpStream::new().append(&1_u8).finish_list();
+    }
+}
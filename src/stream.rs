@@ -0,0 +1,181 @@
+use crate::{decode::decode_item, Decodable, DecodeError, Encodable, IncrementalDecoder};
+use bytes::{Bytes, BytesMut};
+use std::io::{self, Read, Write};
+
+pub(crate) fn invalid_data(err: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Reads back-to-back RLP items, such as the blocks of a Geth export file,
+/// one at a time.
+///
+/// Only the bytes of the next item are read, so at most `max_item_size` bytes
+/// are buffered, and the reader is left right after the last item read. Wrap
+/// unbuffered readers like `File` in a `BufReader`, as headers are read in
+/// small pieces. Decoding errors are reported as [`io::Error`]s of kind
+/// [`io::ErrorKind::InvalidData`] wrapping the [`DecodeError`].
+#[derive(Debug)]
+pub struct RlpReader<R> {
+    reader: R,
+    decoder: IncrementalDecoder,
+}
+
+impl<R: Read> RlpReader<R> {
+    pub fn new(reader: R, max_item_size: usize) -> Self {
+        Self {
+            reader,
+            decoder: IncrementalDecoder::new(max_item_size),
+        }
+    }
+
+    /// Reads the next item, header included. Returns `None` at the end of the
+    /// input, and fails if it ends in the middle of an item.
+    pub fn read_item(&mut self) -> io::Result<Option<Bytes>> {
+        let mut chunk = [0; 4096];
+        loop {
+            let needed = self.decoder.needed().map_err(invalid_data)?;
+            if needed == 0 {
+                return self.decoder.next_item().map_err(invalid_data);
+            }
+
+            let len = needed.min(chunk.len());
+            let n = match self.reader.read(&mut chunk[..len]) {
+                Ok(n) => n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            if n == 0 {
+                if self.decoder.buffered().is_empty() {
+                    return Ok(None);
+                }
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.decoder.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Reads and decodes the next item. Returns `None` at the end of the
+    /// input.
+    pub fn read<T: Decodable>(&mut self) -> io::Result<Option<T>> {
+        self.read_item()?
+            .map(|item| decode_item(item, T::decode_bytes).map_err(invalid_data))
+            .transpose()
+    }
+
+    /// Iterates over the remaining items, decoded as `T`.
+    pub fn iter<T: Decodable>(&mut self) -> impl Iterator<Item = io::Result<T>> + '_ {
+        core::iter::from_fn(move || self.read().transpose())
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Writes RLP items back to back into a writer.
+///
+/// Each item is encoded into a buffer and then written with a single
+/// `write_all`, so unbuffered writers need no `BufWriter`. The buffer is
+/// reused by the next item, so it holds on to as much memory as the largest
+/// item written so far.
+#[derive(Debug)]
+pub struct RlpWriter<W> {
+    writer: W,
+    buf: BytesMut,
+}
+
+impl<W: Write> RlpWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            buf: BytesMut::new(),
+        }
+    }
+
+    pub fn write<T: Encodable + ?Sized>(&mut self, item: &T) -> io::Result<()> {
+        // The buffer is kept to be reused by the next item.
+        self.buf.clear();
+        item.encode(&mut self.buf);
+        self.writer.write_all(&self.buf)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    /// Returns one byte per read.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn rlp_reader_writer() {
+        let mut writer = RlpWriter::new(Vec::new());
+        writer.write(&vec![1_u64, 2, 3]).unwrap();
+        writer.write(&Vec::<u64>::new()).unwrap();
+        writer.write(&vec![0xFFCCB5_u64; 20]).unwrap();
+        writer.flush().unwrap();
+        let out = writer.into_inner();
+
+        let items: Vec<Vec<u64>> = RlpReader::new(&out[..], 128)
+            .iter()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(items, [vec![1, 2, 3], vec![], vec![0xFFCCB5; 20]]);
+
+        let mut reader = RlpReader::new(Trickle(&out), 128);
+        assert_eq!(reader.read_item().unwrap().unwrap()[..], hex!("c3010203"));
+        assert_eq!(reader.read::<Vec<u64>>().unwrap(), Some(vec![]));
+        assert_eq!(reader.read::<Vec<u64>>().unwrap().unwrap().len(), 20);
+        assert_eq!(reader.read::<Vec<u64>>().unwrap(), None);
+    }
+
+    #[test]
+    fn rlp_reader_errors() {
+        let mut reader = RlpReader::new(&hex!("c3010203c301")[..], 128);
+        assert!(reader.read_item().unwrap().is_some());
+        let err = reader.read_item().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // Only the header of the oversized item is read.
+        let data = hex!("b90100");
+        let mut reader = RlpReader::new(&data[..], 128);
+        let err = reader.read_item().unwrap_err();
+        assert_eq!(
+            err.get_ref().unwrap().downcast_ref::<DecodeError>(),
+            Some(&DecodeError::ItemTooLarge {
+                length: 259,
+                max: 128
+            })
+        );
+
+        let mut reader = RlpReader::new(&hex!("c3010203")[..], 128);
+        let err = reader.read::<u64>().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -30,6 +30,17 @@ struct Test4NumbersGenerics<'a, D: Encodable> {
 fn encoded<T: Encodable>(t: &T) -> BytesMut {
     let mut out = BytesMut::new();
     t.encode(&mut out);
+
+    // The statically dispatched path writes the same bytes.
+    let mut out_to = Vec::new();
+    t.encode_to(&mut out_to);
+    assert_eq!(out_to, out);
+
+    // So does the one caching list lengths.
+    let mut out_cached = BytesMut::new();
+    encode_cached(t, &mut out_cached);
+    assert_eq!(out_cached, out);
+
     out
 }
 
@@ -67,7 +78,7 @@ fn test_encode_item() {
     let decoded = Decodable::decode(&mut &*expected).unwrap();
     assert_eq!(item, decoded);
 
-    let mut rlp_view = Rlp::new(&*expected).unwrap();
+    let mut rlp_view = Rlp::new(&expected).unwrap();
     assert_eq!(rlp_view.get_next().unwrap(), Some(item.a));
     assert_eq!(rlp_view.get_next().unwrap(), Some(item.b));
     assert_eq!(rlp_view.get_next().unwrap(), Some(item.c));
@@ -87,3 +98,893 @@ fn test_encode_item() {
     assert_eq!(encoded(&W(item)), expected);
     assert_eq!(W::decode(&mut &*expected).unwrap().0, decoded);
 }
+
+#[test]
+fn test_try_encode_fixed_size() {
+    let item = Test4Numbers {
+        a: 0x05,
+        b: 0xdeadbeefbaadcafe,
+        c: U256::new(0x1234),
+        d: U256::MAX,
+    };
+    let expected = encoded(&item);
+    assert_eq!(expected.len(), 47);
+
+    let out = fastrlp::try_encode_fixed_size::<_, 47>(&item).unwrap();
+    assert_eq!(*out, *expected);
+    assert_eq!(
+        fastrlp::try_encode_fixed_size::<_, 46>(&item),
+        Err(EncodeError::BufferTooSmall {
+            length: 47,
+            capacity: 46
+        })
+    );
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+struct OldHeader {
+    number: u64,
+    gas_limit: u64,
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+struct NewHeader {
+    number: u64,
+    gas_limit: u64,
+    #[rlp(default)]
+    base_fee: u64,
+    #[rlp(default)]
+    extra: Bytes,
+}
+
+#[test]
+fn test_default_fields() {
+    let old = OldHeader {
+        number: 0x10,
+        gas_limit: 0x1234,
+    };
+    let old_encoded = encoded(&old);
+    assert_eq!(
+        NewHeader::decode(&mut &*old_encoded).unwrap(),
+        NewHeader {
+            number: old.number,
+            gas_limit: old.gas_limit,
+            base_fee: 0,
+            extra: Bytes::new(),
+        }
+    );
+
+    let new = NewHeader {
+        number: 0x10,
+        gas_limit: 0x1234,
+        base_fee: 7,
+        extra: Bytes::from_static(b"dog"),
+    };
+    let new_encoded = encoded(&new);
+    assert_eq!(&*new_encoded, hex!("c9108212340783646f67"));
+    assert_eq!(new_encoded.len(), new.length());
+    assert_eq!(NewHeader::decode(&mut &*new_encoded).unwrap(), new);
+
+    // Only the last default field is missing.
+    let partial = hex!("c51082123407");
+    assert_eq!(
+        NewHeader::decode(&mut &partial[..]).unwrap(),
+        NewHeader {
+            extra: Bytes::new(),
+            ..new
+        }
+    );
+
+    // Defaults don't hide malformed fields.
+    assert_eq!(
+        NewHeader::decode(&mut &hex!("c610821234c107")[..]),
+        Err(DecodeError::UnexpectedList)
+    );
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+#[repr(u8)]
+enum Message {
+    Ping,
+    Pong(u64),
+    Hello { version: u8, name: Bytes },
+    Disconnect = 0x10,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+#[rlp(tag = "type_byte")]
+#[repr(u8)]
+enum TypedTx {
+    AccessList { nonce: u64 } = 1,
+    DynamicFee { nonce: u64, max_fee: u64 },
+}
+
+// Type byte enums are a single item, so they can be framed and nested.
+#[test]
+fn test_type_byte_envelope() {
+    let tx = TypedTx::AccessList { nonce: 9 };
+
+    let mut decoder = IncrementalDecoder::new(16);
+    decoder.extend_from_slice(&hex!("8301c109"));
+    assert_eq!(decoder.decode_next(), Ok(Some(tx)));
+
+    let mut reader = RlpReader::new(&hex!("8301c109")[..], 16);
+    assert_eq!(reader.read().unwrap(), Some(tx));
+
+    let mut buf = bytes::Buf::chain(
+        Bytes::from_static(&hex!("8301")),
+        Bytes::from_static(&hex!("c109")),
+    );
+    assert_eq!(TypedTx::decode_from_buf(&mut buf), Ok(tx));
+
+    let txs = vec![
+        TypedTx::AccessList { nonce: 5 },
+        TypedTx::AccessList { nonce: 6 },
+    ];
+    let out = encoded(&txs);
+    assert_eq!(*out, hex!("c88301c1058301c106"));
+    assert_eq!(RlpNode::new(&out).unwrap().item_count(), Ok(2));
+    assert_eq!(Vec::<TypedTx>::decode(&mut &*out), Ok(txs));
+
+    // The envelope must hold exactly the typed encoding.
+    assert_eq!(
+        TypedTx::decode(&mut &hex!("8401c10900")[..]),
+        Err(DecodeError::ListLengthMismatch {
+            expected: 4,
+            got: 3
+        })
+    );
+    assert_eq!(
+        TypedTx::decode(&mut &hex!("c301c109")[..]),
+        Err(DecodeError::UnexpectedList)
+    );
+    let err = TypedTx::decode_with_context(&mut &hex!("8401c28100")[..]).unwrap_err();
+    assert_eq!(err.error, DecodeError::NonCanonicalSingleByte);
+    assert_eq!(err.offset, 3);
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+#[rlp(tag = "integer")]
+enum Direction {
+    Inbound = 1,
+    Outbound,
+}
+
+#[test]
+fn test_enums() {
+    for (msg, expected) in [
+        (Message::Ping, &hex!("c180")[..]),
+        (Message::Pong(0x1234), &hex!("c401821234")[..]),
+        (
+            Message::Hello {
+                version: 5,
+                name: Bytes::from_static(b"dog"),
+            },
+            &hex!("c6020583646f67")[..],
+        ),
+        (Message::Disconnect, &hex!("c110")[..]),
+    ] {
+        let out = encoded(&msg);
+        assert_eq!(&*out, expected);
+        assert_eq!(out.len(), msg.length());
+        assert_eq!(Message::decode(&mut &*out).unwrap(), msg);
+    }
+    assert_eq!(
+        Message::decode(&mut &hex!("c104")[..]),
+        Err(DecodeError::UnknownTag(4))
+    );
+    // Fields are read from the variant's list only.
+    assert_eq!(
+        Message::decode(&mut &hex!("c101821234")[..]),
+        Err(DecodeError::InputTooShort)
+    );
+    assert_eq!(
+        Message::decode(&mut &hex!("c5018212340f")[..]),
+        Err(DecodeError::ListLengthMismatch {
+            expected: 5,
+            got: 4
+        })
+    );
+
+    for (tx, expected) in [
+        (TypedTx::AccessList { nonce: 9 }, &hex!("01c109")[..]),
+        (
+            TypedTx::DynamicFee {
+                nonce: 9,
+                max_fee: 0x80,
+            },
+            &hex!("02c3098180")[..],
+        ),
+    ] {
+        // Encodable wraps the EIP-2718 encoding in a byte string.
+        let out = encoded(&tx);
+        assert_eq!(out[..], *encoded(&Bytes::copy_from_slice(expected)));
+        assert_eq!(out.len(), tx.length());
+        assert_eq!(TypedTx::decode(&mut &*out).unwrap(), tx);
+
+        let mut out = BytesMut::new();
+        tx.encode_2718(&mut out);
+        assert_eq!(&*out, expected);
+        assert_eq!(out.len(), tx.length_2718());
+        let mut buf = &out[..];
+        assert_eq!(TypedTx::decode_2718(&mut buf), Ok(tx));
+        assert!(buf.is_empty());
+    }
+    assert_eq!(
+        TypedTx::decode_2718(&mut &hex!("03c109")[..]),
+        Err(DecodeError::UnknownTag(3))
+    );
+    assert_eq!(
+        TypedTx::decode(&mut &hex!("8303c109")[..]),
+        Err(DecodeError::UnknownTag(3))
+    );
+
+    for (dir, expected) in [(Direction::Inbound, 0x01), (Direction::Outbound, 0x02)] {
+        let out = encoded(&dir);
+        assert_eq!(&*out, [expected]);
+        assert_eq!(out.len(), dir.length());
+        assert_eq!(Direction::decode(&mut &*out).unwrap(), dir);
+    }
+    assert_eq!(
+        Direction::decode(&mut &hex!("80")[..]),
+        Err(DecodeError::UnknownTag(0))
+    );
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable, RlpMaxEncodedLen)]
+#[rlp(trailing)]
+struct TrailingHeader {
+    number: u64,
+    base_fee: Option<u64>,
+    withdrawals_root: Option<[u8; 4]>,
+}
+
+#[test]
+fn test_trailing_fields() {
+    for (header, expected) in [
+        (
+            TrailingHeader {
+                number: 0x10,
+                base_fee: None,
+                withdrawals_root: None,
+            },
+            &hex!("c110")[..],
+        ),
+        (
+            TrailingHeader {
+                number: 0x10,
+                base_fee: Some(7),
+                withdrawals_root: None,
+            },
+            &hex!("c21007")[..],
+        ),
+        (
+            TrailingHeader {
+                number: 0x10,
+                base_fee: Some(0),
+                withdrawals_root: Some(hex!("deadbeef")),
+            },
+            &hex!("c7108084deadbeef")[..],
+        ),
+    ] {
+        let out = encoded(&header);
+        assert_eq!(&*out, expected);
+        assert_eq!(out.len(), header.length());
+        assert_eq!(TrailingHeader::decode(&mut &*out).unwrap(), header);
+        assert!(out.len() <= <TrailingHeader as MaxEncodedLenAssoc>::LEN);
+    }
+}
+
+#[test]
+#[should_panic(expected = "a None field must not be followed by a Some field")]
+fn test_trailing_fields_gap() {
+    encoded(&TrailingHeader {
+        number: 0x10,
+        base_fee: None,
+        withdrawals_root: Some(hex!("deadbeef")),
+    });
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable, RlpMaxEncodedLen)]
+struct CachedHash {
+    #[rlp(skip)]
+    hash: u64,
+    a: u8,
+    #[rlp(skip)]
+    sender: u64,
+    b: u64,
+}
+
+#[test]
+fn test_skip_fields() {
+    let item = CachedHash {
+        hash: 0xdead,
+        a: 1,
+        sender: 0xbeef,
+        b: 2,
+    };
+    let out = encoded(&item);
+    assert_eq!(&*out, hex!("c20102"));
+    assert_eq!(out.len(), item.length());
+    assert_eq!(
+        CachedHash::decode(&mut &*out).unwrap(),
+        CachedHash {
+            hash: 0,
+            sender: 0,
+            ..item
+        }
+    );
+    assert_eq!(
+        <CachedHash as MaxEncodedLenAssoc>::LEN,
+        1 + <u8 as MaxEncodedLenAssoc>::LEN + <u64 as MaxEncodedLenAssoc>::LEN
+    );
+}
+
+mod byte_string {
+    use super::*;
+
+    pub fn encode(v: &[u8], out: &mut dyn BufMut) {
+        v.encode(out)
+    }
+
+    pub fn length(v: &[u8]) -> usize {
+        v.length()
+    }
+
+    pub fn decode(buf: &mut &[u8]) -> Result<Vec<u8>, DecodeError> {
+        BytesMut::decode(buf).map(|b| b.to_vec())
+    }
+}
+
+mod duration_secs {
+    use super::*;
+    use core::time::Duration;
+
+    pub const LEN: usize = <u64 as MaxEncodedLenAssoc>::LEN;
+
+    pub fn encode(v: &Duration, out: &mut dyn BufMut) {
+        v.as_secs().encode(out)
+    }
+
+    pub fn length(v: &Duration) -> usize {
+        v.as_secs().length()
+    }
+
+    pub fn decode(buf: &mut &[u8]) -> Result<Duration, DecodeError> {
+        u64::decode(buf).map(Duration::from_secs)
+    }
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+struct WithCodec {
+    #[rlp(with = "byte_string")]
+    data: Vec<u8>,
+    #[rlp(with = "duration_secs")]
+    timestamp: core::time::Duration,
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable, RlpMaxEncodedLen)]
+struct WithCodecFixed {
+    a: u8,
+    #[rlp(with = "duration_secs")]
+    timestamp: core::time::Duration,
+}
+
+#[test]
+fn test_with_codec() {
+    let item = WithCodec {
+        data: b"dog".to_vec(),
+        timestamp: core::time::Duration::from_secs(0x0400),
+    };
+    let out = encoded(&item);
+    assert_eq!(&*out, hex!("c783646f67820400"));
+    assert_eq!(out.len(), item.length());
+    assert_eq!(WithCodec::decode(&mut &*out).unwrap(), item);
+
+    let item = WithCodecFixed {
+        a: 1,
+        timestamp: core::time::Duration::from_secs(2),
+    };
+    assert_eq!(&*fastrlp::encode_fixed_size(&item), hex!("c20102"));
+    assert_eq!(
+        <WithCodecFixed as MaxEncodedLenAssoc>::LEN,
+        1 + <u8 as MaxEncodedLenAssoc>::LEN + <u64 as MaxEncodedLenAssoc>::LEN
+    );
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+struct Tagged<T, M> {
+    value: T,
+    values: Vec<T>,
+    #[rlp(skip)]
+    marker: core::marker::PhantomData<M>,
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable, RlpMaxEncodedLen)]
+struct GenericFixed<T> {
+    a: u8,
+    b: T,
+}
+
+#[derive(Debug, PartialEq, RlpEncodableWrapper, RlpDecodableWrapper)]
+struct GenericWrapper<T> {
+    inner: T,
+}
+
+#[derive(RlpEncodable)]
+#[rlp(bound = "T: Encodable + core::fmt::Debug")]
+struct Bounded<T> {
+    value: T,
+}
+
+#[derive(Debug, PartialEq)]
+struct NotRlp;
+
+#[test]
+fn test_generic_bounds() {
+    // `NotRlp` is never encoded, so it needs no impls.
+    let item = Tagged::<u64, NotRlp> {
+        value: 1,
+        values: vec![2, 3],
+        marker: core::marker::PhantomData,
+    };
+    let out = encoded(&item);
+    assert_eq!(&*out, hex!("c401c20203"));
+    assert_eq!(out.len(), item.length());
+    assert_eq!(Tagged::decode(&mut &*out).unwrap(), item);
+
+    let item = GenericFixed {
+        a: 1,
+        b: 0x0400_u16,
+    };
+    let out = encoded(&item);
+    assert_eq!(&*out, hex!("c401820400"));
+    assert_eq!(GenericFixed::decode(&mut &*out).unwrap(), item);
+    assert_eq!(
+        <GenericFixed<u16> as MaxEncodedLenAssoc>::LEN,
+        1 + <u8 as MaxEncodedLenAssoc>::LEN + <u16 as MaxEncodedLenAssoc>::LEN
+    );
+
+    let item = GenericWrapper { inner: 0x0400_u16 };
+    let out = encoded(&item);
+    assert_eq!(&*out, hex!("820400"));
+    assert_eq!(GenericWrapper::decode(&mut &*out).unwrap(), item);
+
+    assert_eq!(&*encoded(&Bounded { value: 7_u8 }), hex!("c107"));
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable, RlpMaxEncodedLen)]
+struct Signature {
+    v: u64,
+    r: U256,
+    s: U256,
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable, RlpMaxEncodedLen)]
+struct SignedTx {
+    nonce: u64,
+    #[rlp(flatten)]
+    signature: Signature,
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+struct GenericSigned<S> {
+    nonce: u64,
+    #[rlp(flatten)]
+    signature: S,
+}
+
+#[test]
+fn test_flatten() {
+    let tx = SignedTx {
+        nonce: 9,
+        signature: Signature {
+            v: 1,
+            r: U256::new(2),
+            s: U256::new(3),
+        },
+    };
+    let out = encoded(&tx);
+    assert_eq!(&*out, hex!("c409010203"));
+    assert_eq!(out.len(), tx.length());
+    assert_eq!(SignedTx::decode(&mut &*out).unwrap(), tx);
+    assert_eq!(&*fastrlp::encode_fixed_size(&tx), &*out);
+    let payload_len = 2 * <u64 as MaxEncodedLenAssoc>::LEN + 2 * <U256 as MaxEncodedLenAssoc>::LEN;
+    assert_eq!(
+        <SignedTx as MaxEncodedLenAssoc>::LEN,
+        length_of_length(payload_len) + payload_len
+    );
+
+    let generic = GenericSigned {
+        nonce: tx.nonce,
+        signature: tx.signature,
+    };
+    assert_eq!(encoded(&generic), out);
+    assert_eq!(GenericSigned::decode(&mut &*out).unwrap(), generic);
+
+    // The flattened fields must be inline, not a nested list.
+    assert_eq!(
+        SignedTx::decode(&mut &hex!("c509c3010203")[..]),
+        Err(DecodeError::UnexpectedList)
+    );
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+#[rlp(allow_trailing)]
+struct Hello {
+    version: u8,
+    client: Bytes,
+}
+
+#[test]
+fn test_allow_trailing() {
+    let hello = Hello {
+        version: 5,
+        client: Bytes::from_static(b"dog"),
+    };
+    let out = encoded(&hello);
+    assert_eq!(Hello::decode(&mut &*out).unwrap(), hello);
+
+    // Newer peers may send more elements; they are ignored.
+    let mut buf = &hex!("ca0583646f67c20102818001")[..];
+    assert_eq!(Hello::decode(&mut buf).unwrap(), hello);
+    assert_eq!(buf, hex!("01"));
+
+    assert_eq!(
+        Hello::decode(&mut &hex!("c70583646f67c301")[..]),
+        Err(DecodeError::InputTooShort)
+    );
+
+    // Without the attribute, extra elements are an error.
+    assert_eq!(
+        Item::decode(&mut &hex!("c583646f6701")[..]),
+        Err(DecodeError::ListLengthMismatch {
+            expected: 5,
+            got: 4
+        })
+    );
+}
+
+#[derive(Debug, PartialEq, RlpEncodable)]
+struct TxOwned {
+    nonce: u64,
+    to: [u8; 4],
+    input: Bytes,
+    memo: String,
+}
+
+#[derive(Clone, Debug, PartialEq, RlpDecodable)]
+struct TxRef<'a> {
+    nonce: u64,
+    #[rlp(borrow)]
+    to: &'a [u8; 4],
+    #[rlp(borrow)]
+    input: &'a [u8],
+    #[rlp(borrow)]
+    memo: &'a str,
+}
+
+#[derive(Debug, PartialEq, RlpDecodable)]
+struct BlockRef<'a> {
+    number: u64,
+    #[rlp(borrow)]
+    txs: Vec<TxRef<'a>>,
+}
+
+// Lifetimes alone don't make a struct borrow from the buffer.
+#[derive(Debug, PartialEq, RlpDecodable)]
+struct Marker<'a> {
+    x: u64,
+    #[rlp(skip)]
+    p: std::marker::PhantomData<&'a ()>,
+}
+
+#[test]
+fn test_borrowed() {
+    let tx = TxOwned {
+        nonce: 7,
+        to: hex!("deadbeef"),
+        input: Bytes::from_static(&hex!("6001600155")),
+        memo: "hello".into(),
+    };
+    let out = encoded(&tx);
+    let mut buf = &out[..];
+    let decoded = TxRef::borrow_decode(&mut buf).unwrap();
+    assert!(buf.is_empty());
+    assert_eq!(
+        decoded,
+        TxRef {
+            nonce: 7,
+            to: &hex!("deadbeef"),
+            input: &hex!("6001600155"),
+            memo: "hello",
+        }
+    );
+    // The byte fields point into the input buffer.
+    let range = out.as_ptr_range();
+    assert!(range.contains(&decoded.input.as_ptr()));
+    assert!(range.contains(&decoded.memo.as_ptr()));
+
+    let out = encoded(&(42u64, vec![&tx, &tx]));
+    let block = BlockRef::borrow_decode(&mut &out[..]).unwrap();
+    assert_eq!(block.number, 42);
+    assert_eq!(block.txs, [decoded.clone(), decoded]);
+    let range = out.as_ptr_range();
+    assert!(range.contains(&block.txs[1].input.as_ptr()));
+
+    assert_eq!(
+        Vec::<Marker>::decode(&mut &hex!("c4c101c102")[..]),
+        Ok(vec![
+            Marker {
+                x: 1,
+                p: std::marker::PhantomData
+            },
+            Marker {
+                x: 2,
+                p: std::marker::PhantomData
+            },
+        ])
+    );
+
+    assert_eq!(
+        TxRef::borrow_decode(&mut &hex!("c70783deadbe8080")[..]),
+        Err(DecodeError::UnexpectedLength)
+    );
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+struct BranchNode {
+    #[rlp(with = "fastrlp::fixed_list")]
+    children: [ethereum_types::H256; 16],
+    value: Option<Bytes>,
+    meta: (u64, bool),
+}
+
+#[test]
+fn test_container_impls() {
+    let mut children = [ethereum_types::H256::zero(); 16];
+    children[3] = ethereum_types::H256::repeat_byte(0x11);
+    let node = BranchNode {
+        children,
+        value: None,
+        meta: (1, true),
+    };
+    let out = encoded(&node);
+    assert_eq!(out.len(), node.length());
+    assert_eq!(&out[out.len() - 4..], hex!("c0c20101"));
+    assert_eq!(BranchNode::decode(&mut &*out).unwrap(), node);
+
+    let node = BranchNode {
+        value: Some(Bytes::from_static(b"dog")),
+        ..node
+    };
+    let out = encoded(&node);
+    assert_eq!(&out[out.len() - 8..], hex!("c483646f67c20101"));
+    assert_eq!(BranchNode::decode(&mut &*out).unwrap(), node);
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable, RlpMaxEncodedLen)]
+struct BoundedBranchNode {
+    children: FixedList<ethereum_types::H256, 16>,
+    value: u64,
+}
+
+#[test]
+fn test_fixed_list() {
+    let mut children = [ethereum_types::H256::zero(); 16];
+    children[3] = ethereum_types::H256::repeat_byte(0x11);
+    let node = BoundedBranchNode {
+        children: FixedList(children),
+        value: 7,
+    };
+    let out = encoded(&node);
+    assert_eq!(*fastrlp::encode_fixed_size(&node), *out);
+    assert_eq!(BoundedBranchNode::decode(&mut &*out).unwrap(), node);
+
+    // 16 hashes of 33 bytes behind a 3-byte header, and a 9-byte integer.
+    assert_eq!(
+        <BoundedBranchNode as MaxEncodedLenAssoc>::LEN,
+        3 + (3 + 16 * 33) + 9
+    );
+
+    // The same encoding as the `with` module.
+    let branch = BranchNode {
+        children,
+        value: None,
+        meta: (1, true),
+    };
+    let prefix = encoded(&node.children);
+    assert_eq!(encoded(&branch)[3..3 + prefix.len()], prefix[..]);
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+#[rlp(trailing)]
+struct ByteVecs {
+    #[rlp(bytes)]
+    data: Vec<u8>,
+    list: Vec<u8>,
+    #[rlp(bytes)]
+    boxed: Box<[u8]>,
+    #[rlp(bytes)]
+    extra: Option<Vec<u8>>,
+}
+
+#[derive(Debug, PartialEq, RlpEncodableWrapper, RlpDecodableWrapper)]
+struct ByteWrapper(#[rlp(bytes)] Vec<u8>);
+
+#[test]
+fn test_bytes_attr() {
+    let v = ByteVecs {
+        data: hex!("abba").to_vec(),
+        list: hex!("abba").to_vec(),
+        boxed: Box::new(hex!("7b")),
+        extra: None,
+    };
+    let out = encoded(&v);
+    assert_eq!(out[..], hex!("c982abbac481ab81ba7b")[..]);
+    assert_eq!(ByteVecs::decode(&mut &*out).unwrap(), v);
+
+    let v = ByteVecs {
+        extra: Some(b"dog".to_vec()),
+        ..v
+    };
+    let out = encoded(&v);
+    assert_eq!(out[..], hex!("cd82abbac481ab81ba7b83646f67")[..]);
+    assert_eq!(ByteVecs::decode(&mut &*out).unwrap(), v);
+
+    let w = ByteWrapper(hex!("abba").to_vec());
+    let out = encoded(&w);
+    assert_eq!(out[..], hex!("82abba")[..]);
+    assert_eq!(out.len(), w.length());
+    assert_eq!(ByteWrapper::decode(&mut &*out).unwrap(), w);
+    assert_eq!(ByteWrapper::decode_bytes(&mut out.freeze()).unwrap(), w);
+    assert_eq!(
+        ByteWrapper::decode_with_context(&mut &hex!("c0")[..])
+            .unwrap_err()
+            .error,
+        DecodeError::UnexpectedList
+    );
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+struct Frame {
+    id: u64,
+    payload: Bytes,
+    #[rlp(flatten)]
+    signature: Signature,
+    chunks: Vec<Bytes>,
+    #[rlp(default)]
+    tail: Bytes,
+}
+
+#[test]
+fn test_decode_bytes() {
+    let v = Frame {
+        id: 5,
+        payload: Bytes::from_static(b"horse"),
+        signature: Signature {
+            v: 1,
+            r: U256::new(2),
+            s: U256::new(3),
+        },
+        chunks: vec![Bytes::from_static(b"cat"), Bytes::new()],
+        tail: Bytes::from_static(b"dog"),
+    };
+    let out = Bytes::from(encoded(&v).to_vec());
+    assert_eq!(
+        out[..],
+        hex!("d40585686f727365010203c5836361748083646f67")[..]
+    );
+    let start = out.as_ptr() as usize;
+
+    let mut buf = out.clone();
+    let decoded = Frame::decode_bytes(&mut buf).unwrap();
+    assert_eq!(decoded, v);
+    assert!(buf.is_empty());
+    // The byte fields point into `out` instead of being copied.
+    assert_eq!(decoded.payload.as_ptr() as usize, start + 3);
+    assert_eq!(decoded.chunks[0].as_ptr() as usize, start + 13);
+    assert_eq!(decoded.tail.as_ptr() as usize, start + 18);
+
+    // Missing default fields and extra elements behave as with `decode`.
+    let out = Bytes::from_static(&hex!("d00585686f727365010203c58363617480"));
+    assert_eq!(
+        Frame::decode_bytes(&mut out.clone()),
+        Ok(Frame {
+            tail: Bytes::new(),
+            ..v
+        })
+    );
+    let out = Bytes::from_static(&hex!("d50585686f727365010203c5836361748083646f6780"));
+    assert_eq!(
+        Frame::decode_bytes(&mut out.clone()),
+        Err(DecodeError::ListLengthMismatch {
+            expected: 21,
+            got: 20
+        })
+    );
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+struct AccessItem<K> {
+    address: [u8; 4],
+    storage_keys: Vec<K>,
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+struct ContextTx<K> {
+    nonce: u64,
+    access_list: Vec<AccessItem<K>>,
+}
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+struct ContextBlock<K> {
+    number: u64,
+    transactions: Vec<ContextTx<K>>,
+}
+
+#[test]
+fn test_error_context() {
+    let item = |key: Bytes| AccessItem {
+        address: hex!("deadbeef"),
+        storage_keys: vec![key],
+    };
+    let tx = |key: Bytes| ContextTx {
+        nonce: 1,
+        access_list: vec![item(Bytes::from_static(&hex!("01"))), item(key)],
+    };
+    // The storage key of the second transaction's second access list item
+    // has a leading zero.
+    let block = ContextBlock {
+        number: 5,
+        transactions: vec![
+            tx(Bytes::from_static(&hex!("02"))),
+            tx(Bytes::from_static(&hex!("0003"))),
+        ],
+    };
+    let out = encoded(&block);
+
+    let err = ContextBlock::<u64>::decode(&mut &*out).unwrap_err();
+    assert_eq!(err, DecodeError::LeadingZero);
+
+    let err = ContextBlock::<u64>::decode_with_context(&mut &*out).unwrap_err();
+    assert_eq!(err.error, DecodeError::LeadingZero);
+    assert_eq!(&out[err.offset..][..3], hex!("820003"));
+    assert_eq!(err.root, Some("ContextBlock"));
+    assert_eq!(
+        err.path().copied().collect::<Vec<_>>(),
+        [
+            PathSegment::Field("transactions"),
+            PathSegment::Index(1),
+            PathSegment::Field("access_list"),
+            PathSegment::Index(1),
+            PathSegment::Field("storage_keys"),
+            PathSegment::Index(0),
+        ]
+    );
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "leading zero at byte {} in ContextBlock.transactions[1].access_list[1].storage_keys[0]",
+            err.offset
+        )
+    );
+
+    let out = encoded(&ContextBlock::<u64> {
+        number: 5,
+        transactions: vec![],
+    });
+    assert!(ContextBlock::<u64>::decode_with_context(&mut &*out).is_ok());
+
+    let err = Vec::<ContextTx<u64>>::decode_with_context(&mut &hex!("c4c3018180")[..]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unexpected string at byte 3 in [0].access_list"
+    );
+
+    let err = Message::decode_with_context(&mut &hex!("c50282010080")[..]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "overflow at byte 2 in Message::Hello.version"
+    );
+}